@@ -0,0 +1,431 @@
+// Allow `cargo stylus export-abi` to generate a main function.
+#![cfg_attr(not(feature = "export-abi"), no_main)]
+extern crate alloc;
+
+/// Import items from the SDK. The prelude contains common traits and macros.
+use stylus_sdk::{abi::Bytes, alloy_primitives::{Address, FixedBytes, U256}, block, call::{transfer_eth, Call}, contract, evm, msg, prelude::*};
+use alloy_sol_types::{sol, sol_data::Uint, SolType};
+
+// Import the IERC721 interface.
+sol_interface! {
+    interface IERC721 {
+        function transferFrom(address from, address to, uint256 token_id) external;
+    }
+}
+
+// Import the IERC2981 interface, used to pay royalties on settlement.
+sol_interface! {
+    interface IERC2981 {
+        function royaltyInfo(uint256 token_id, uint256 sale_price) external view returns (address receiver, uint256 royalty_amount);
+    }
+}
+
+// The parameters of an auction: reserve price, minimum increment in basis points, and duration.
+type AuctionParams = (Uint<256>, Uint<256>, Uint<256>);
+
+// Define the events and errors for the contract.
+sol!{
+    // Define the events for the contract.
+    event AuctionCreated(uint256 indexed auctionId, address indexed seller, address indexed nft, uint256 tokenId, uint256 reservePrice, uint256 endAt); // Create an auction.
+    event Bid(uint256 indexed auctionId, address indexed sender, uint256 amount); // Bid on an auction.
+    event Withdraw(address indexed account, uint256 amount); // Withdraw pending funds.
+    event AuctionSettled(uint256 indexed auctionId, address indexed winner, uint256 amount, uint256 fee, uint256 royalty); // Settle an auction.
+    event AuctionCancelled(uint256 indexed auctionId); // Cancel an auction without bids.
+    event FeeChanged(address indexed recipient, uint256 feeBps); // Change the protocol fee.
+
+    // Define the errors for the contract.
+    error AlreadyInitialized(); // The contract has already been initialized.
+    error NotOwner(); // The sender is not the owner of the auction house.
+    error InvalidFee(uint256 feeBps); // The protocol fee is above the maximum.
+    error InvalidDuration(); // The duration of the auction is zero.
+    error InvalidParams(); // The auction parameters could not be decoded.
+    error AuctionNotFound(uint256 auctionId); // The auction does not exist.
+    error AuctionEnded(uint256 auctionId); // The auction has ended.
+    error AuctionNotEnded(uint256 auctionId); // The auction has not ended.
+    error AuctionSettledAlready(uint256 auctionId); // The auction has already been settled or cancelled.
+    error BidTooLow(uint256 minBid); // The bid is too low.
+    error NotSeller(); // The sender is not the seller.
+    error HasBids(); // The auction already has bids.
+    error NftTransferFailed(bytes reason); // A transfer of the NFT failed.
+    error TransferFailed(bytes reason); // A transfer of ETH failed.
+}
+
+#[derive(SolidityError)]
+pub enum AuctionHouseError {
+    // Define the errors for the contract.
+    AlreadyInitialized(AlreadyInitialized),
+    NotOwner(NotOwner),
+    InvalidFee(InvalidFee),
+    InvalidDuration(InvalidDuration),
+    InvalidParams(InvalidParams),
+    AuctionNotFound(AuctionNotFound),
+    AuctionEnded(AuctionEnded),
+    AuctionNotEnded(AuctionNotEnded),
+    AuctionSettledAlready(AuctionSettledAlready),
+    BidTooLow(BidTooLow),
+    NotSeller(NotSeller),
+    HasBids(HasBids),
+    NftTransferFailed(NftTransferFailed),
+    TransferFailed(TransferFailed),
+}
+
+// Define some persistent storage using the Solidity ABI.
+// `AuctionHouse` will be the entrypoint.
+sol_storage! {
+    #[entrypoint]
+    pub struct AuctionHouse {
+        address owner; // The owner of the auction house, who sets the protocol fee.
+        address fee_recipient; // The address receiving the protocol fee.
+        uint256 fee_bps; // The protocol fee in basis points of the winning bid.
+        uint256 auction_count; // The number of auctions created, auction IDs start at 1.
+        mapping(uint256 => Auction) auctions; // The auctions by ID.
+        mapping(address => uint256) pending_returns; // Outbid bids and failed payouts, withdrawable by their owner.
+    }
+
+    // A single auction.
+    pub struct Auction {
+        address seller; // The address of the seller.
+        address nft; // The address of the NFT contract.
+        uint256 token_id; // The ID of the NFT.
+        uint256 reserve_price; // The lowest amount the first bid can be.
+        uint256 min_increment_bps; // A bid must exceed the highest bid by at least this many basis points of it.
+        uint256 end_at; // The end time of the auction.
+        address highest_bidder; // The address of the highest bidder.
+        uint256 highest_bid; // The highest bid.
+        bool settled; // The auction has been settled or cancelled.
+    }
+}
+
+/// Declare that `AuctionHouse` is a contract with the following external methods.
+#[public]
+impl AuctionHouse {
+    pub const BPS_DENOMINATOR: u64 = 10_000; // 100% = 10,000 basis points.
+    pub const MAX_FEE_BPS: u64 = 1_000; // The protocol fee is at most 10%.
+
+    // Get owner address
+    pub fn owner(&self) -> Result<Address, AuctionHouseError> {
+        Ok(self.owner.get())
+    }
+
+    // Get fee recipient address
+    pub fn fee_recipient(&self) -> Result<Address, AuctionHouseError> {
+        Ok(self.fee_recipient.get())
+    }
+
+    // Get protocol fee in basis points
+    pub fn fee_bps(&self) -> Result<U256, AuctionHouseError> {
+        Ok(self.fee_bps.get())
+    }
+
+    // Get number of auctions created
+    pub fn auction_count(&self) -> Result<U256, AuctionHouseError> {
+        Ok(self.auction_count.get())
+    }
+
+    // Get funds an account can withdraw
+    pub fn pending_returns(&self, account: Address) -> Result<U256, AuctionHouseError> {
+        Ok(self.pending_returns.get(account))
+    }
+
+    // Get an auction: seller, nft, token id, reserve price, minimum increment, end time, highest bidder, highest bid and settled status
+    #[allow(clippy::type_complexity)]
+    pub fn get_auction(&self, auction_id: U256) -> Result<(Address, Address, U256, U256, U256, U256, Address, U256, bool), AuctionHouseError> {
+        self.check_exists(auction_id)?;
+        let auction = self.auctions.getter(auction_id);
+        Ok((
+            auction.seller.get(),
+            auction.nft.get(),
+            auction.token_id.get(),
+            auction.reserve_price.get(),
+            auction.min_increment_bps.get(),
+            auction.end_at.get(),
+            auction.highest_bidder.get(),
+            auction.highest_bid.get(),
+            auction.settled.get(),
+        ))
+    }
+
+    // Get the lowest amount the next bid on an auction can be
+    pub fn min_next_bid(&self, auction_id: U256) -> Result<U256, AuctionHouseError> {
+        self.check_exists(auction_id)?;
+        let auction = self.auctions.getter(auction_id);
+        let highest_bid = auction.highest_bid.get();
+        // The first bid only has to reach the reserve price.
+        if auction.highest_bidder.get() == Address::default() {
+            return Ok(auction.reserve_price.get().max(U256::from(1)));
+        }
+        // Later bids have to exceed the highest bid by the minimum increment.
+        let increment = highest_bid.saturating_mul(auction.min_increment_bps.get()) / U256::from(Self::BPS_DENOMINATOR);
+        Ok(highest_bid.saturating_add(increment.max(U256::from(1))))
+    }
+
+    // Initialize program
+    pub fn initialize(&mut self, fee_recipient: Address, fee_bps: U256) -> Result<(), AuctionHouseError> {
+        // Check if the contract has already been initialized.
+        if self.owner.get() != Address::default() {
+            // Return an error if the contract has already been initialized.
+            return Err(AuctionHouseError::AlreadyInitialized(AlreadyInitialized{}));
+        }
+
+        // The sender becomes the owner of the auction house.
+        self.owner.set(msg::sender());
+        self.set_fee_unchecked(fee_recipient, fee_bps)
+    }
+
+    // The set_fee method allows the owner to change the protocol fee.
+    pub fn set_fee(&mut self, fee_recipient: Address, fee_bps: U256) -> Result<(), AuctionHouseError> {
+        // Check if the sender is the owner.
+        if self.owner.get() != msg::sender() {
+            return Err(AuctionHouseError::NotOwner(NotOwner{}));
+        }
+        self.set_fee_unchecked(fee_recipient, fee_bps)
+    }
+
+    // The create_auction method lists an NFT, which the seller must have approved the auction house to transfer.
+    // `params` are the reserve price, the minimum increment in basis points and the duration in seconds.
+    pub fn create_auction(&mut self, nft: Address, token_id: U256, params: (U256, U256, U256)) -> Result<U256, AuctionHouseError> {
+        // Transfer the NFT to the auction house.
+        let result = IERC721::new(nft).transfer_from(Call::new_in(self), msg::sender(), contract::address(), token_id);
+        if let Err(err) = result {
+            let reason: Vec<u8> = err.into();
+            return Err(AuctionHouseError::NftTransferFailed(NftTransferFailed{reason: reason.into()}));
+        }
+        self.create(msg::sender(), nft, token_id, params)
+    }
+
+    // The on_erc721_received method lists an NFT sent with `safeTransferFrom`.
+    // `data` must be the ABI encoded parameters of `create_auction`.
+    #[selector(name = "onERC721Received")]
+    pub fn on_erc721_received(&mut self, _operator: Address, from: Address, token_id: U256, data: Bytes) -> Result<FixedBytes<4>, AuctionHouseError> {
+        let params = AuctionParams::abi_decode_params(&data, true)
+            .map_err(|_| AuctionHouseError::InvalidParams(InvalidParams{}))?;
+        // The NFT contract is the sender, and the previous owner is the seller.
+        self.create(from, msg::sender(), token_id, params)?;
+        // Return the selector of `onERC721Received` to accept the NFT.
+        Ok(FixedBytes::new([0x15, 0x0b, 0x7a, 0x02]))
+    }
+
+    // The bid method allows bidders to place a bid on an auction.
+    #[payable]
+    pub fn bid(&mut self, auction_id: U256) -> Result<(), AuctionHouseError> {
+        self.check_active(auction_id)?;
+
+        // Check if the auction has ended.
+        if U256::from(block::timestamp()) >= self.auctions.getter(auction_id).end_at.get() {
+            return Err(AuctionHouseError::AuctionEnded(AuctionEnded{auctionId: auction_id}));
+        }
+
+        // Check if the bid amount is high enough.
+        let min_bid = self.min_next_bid(auction_id)?;
+        if msg::value() < min_bid {
+            return Err(AuctionHouseError::BidTooLow(BidTooLow{minBid: min_bid}));
+        }
+
+        // Refund the previous highest bidder. (But will not transfer back at this call, needs bidders to call withdraw() to get back the fund.
+        let mut auction = self.auctions.setter(auction_id);
+        let previous_bidder = auction.highest_bidder.get();
+        let previous_bid = auction.highest_bid.get();
+        auction.highest_bidder.set(msg::sender());
+        auction.highest_bid.set(msg::value());
+        if previous_bidder != Address::default() {
+            let mut pending = self.pending_returns.setter(previous_bidder);
+            let current = pending.get();
+            pending.set(current + previous_bid);
+        }
+
+        // Log the bid event.
+        evm::log(Bid {
+            auctionId: auction_id,
+            sender: msg::sender(),
+            amount: msg::value(),
+        });
+        Ok(())
+    }
+
+    // The withdraw method allows bidders to withdraw outbid bids and failed payouts.
+    pub fn withdraw(&mut self) -> Result<(), AuctionHouseError> {
+        // Get the pending funds of the sender.
+        let mut pending = self.pending_returns.setter(msg::sender());
+        let amount = pending.get();
+        // Set the record of this account to 0 and transfer back the funds.
+        pending.set(U256::ZERO);
+        if let Err(reason) = transfer_eth(msg::sender(), amount) {
+            return Err(AuctionHouseError::TransferFailed(TransferFailed{reason: reason.into()}));
+        }
+
+        // Log the withdraw event.
+        evm::log(Withdraw {
+            account: msg::sender(),
+            amount,
+        });
+        Ok(())
+    }
+
+    // The settle method ends an auction, anyone can call it once the auction is over.
+    // The NFT goes to the highest bidder, and the highest bid is split between the protocol fee,
+    // the royalty reported by the NFT contract (ERC-2981) and the seller.
+    pub fn settle(&mut self, auction_id: U256) -> Result<(), AuctionHouseError> {
+        self.check_active(auction_id)?;
+        let auction = self.auctions.getter(auction_id);
+        if U256::from(block::timestamp()) < auction.end_at.get() {
+            return Err(AuctionHouseError::AuctionNotEnded(AuctionNotEnded{auctionId: auction_id}));
+        }
+        let seller = auction.seller.get();
+        let nft = auction.nft.get();
+        let token_id = auction.token_id.get();
+        let winner = auction.highest_bidder.get();
+        let amount = auction.highest_bid.get();
+        self.auctions.setter(auction_id).settled.set(true);
+
+        // If there is no highest bidder, return the NFT to the seller.
+        if winner == Address::default() {
+            self.transfer_nft(nft, seller, token_id)?;
+            evm::log(AuctionSettled {
+                auctionId: auction_id,
+                winner,
+                amount: U256::ZERO,
+                fee: U256::ZERO,
+                royalty: U256::ZERO,
+            });
+            return Ok(());
+        }
+
+        // Transfer the NFT to the highest bidder.
+        self.transfer_nft(nft, winner, token_id)?;
+
+        // Pay the protocol fee.
+        let fee = amount * self.fee_bps.get() / U256::from(Self::BPS_DENOMINATOR);
+        self.pay_out(self.fee_recipient.get(), fee);
+
+        // Pay the royalty, if the NFT contract reports one. It is capped at what is left after the fee.
+        let mut royalty = U256::ZERO;
+        if let Ok((receiver, royalty_amount)) = IERC2981::new(nft).royalty_info(Call::new_in(self), token_id, amount) {
+            if receiver != Address::default() {
+                royalty = royalty_amount.min(amount - fee);
+                self.pay_out(receiver, royalty);
+            }
+        }
+
+        // Pay the rest to the seller.
+        self.pay_out(seller, amount - fee - royalty);
+
+        // Log the settle event.
+        evm::log(AuctionSettled {
+            auctionId: auction_id,
+            winner,
+            amount,
+            fee,
+            royalty,
+        });
+        Ok(())
+    }
+
+    // The cancel_auction method allows the seller to cancel an auction without bids and get the NFT back.
+    pub fn cancel_auction(&mut self, auction_id: U256) -> Result<(), AuctionHouseError> {
+        self.check_active(auction_id)?;
+        let auction = self.auctions.getter(auction_id);
+        if auction.seller.get() != msg::sender() {
+            return Err(AuctionHouseError::NotSeller(NotSeller{}));
+        }
+        if auction.highest_bidder.get() != Address::default() {
+            return Err(AuctionHouseError::HasBids(HasBids{}));
+        }
+        let nft = auction.nft.get();
+        let token_id = auction.token_id.get();
+        self.auctions.setter(auction_id).settled.set(true);
+
+        // Return the NFT to the seller.
+        self.transfer_nft(nft, msg::sender(), token_id)?;
+        evm::log(AuctionCancelled { auctionId: auction_id });
+        Ok(())
+    }
+}
+
+// These methods aren't exposed to other contracts.
+impl AuctionHouse {
+    // Returns an error if the auction does not exist.
+    fn check_exists(&self, auction_id: U256) -> Result<(), AuctionHouseError> {
+        if auction_id == U256::ZERO || auction_id > self.auction_count.get() {
+            return Err(AuctionHouseError::AuctionNotFound(AuctionNotFound{auctionId: auction_id}));
+        }
+        Ok(())
+    }
+
+    // Returns an error if the auction does not exist or has been settled or cancelled.
+    fn check_active(&self, auction_id: U256) -> Result<(), AuctionHouseError> {
+        self.check_exists(auction_id)?;
+        if self.auctions.getter(auction_id).settled.get() {
+            return Err(AuctionHouseError::AuctionSettledAlready(AuctionSettledAlready{auctionId: auction_id}));
+        }
+        Ok(())
+    }
+
+    // Sets the protocol fee after checking it is not above the maximum.
+    fn set_fee_unchecked(&mut self, fee_recipient: Address, fee_bps: U256) -> Result<(), AuctionHouseError> {
+        if fee_bps > U256::from(Self::MAX_FEE_BPS) {
+            return Err(AuctionHouseError::InvalidFee(InvalidFee{feeBps: fee_bps}));
+        }
+        self.fee_recipient.set(fee_recipient);
+        self.fee_bps.set(fee_bps);
+        evm::log(FeeChanged {
+            recipient: fee_recipient,
+            feeBps: fee_bps,
+        });
+        Ok(())
+    }
+
+    // Creates an auction for an NFT the auction house already holds.
+    fn create(&mut self, seller: Address, nft: Address, token_id: U256, params: (U256, U256, U256)) -> Result<U256, AuctionHouseError> {
+        let (reserve_price, min_increment_bps, duration) = params;
+        // Check that the auction will run for some time.
+        if duration == U256::ZERO {
+            return Err(AuctionHouseError::InvalidDuration(InvalidDuration{}));
+        }
+
+        // Auction IDs start at 1.
+        let auction_id = self.auction_count.get() + U256::from(1);
+        self.auction_count.set(auction_id);
+        let end_at = U256::from(block::timestamp()) + duration;
+
+        let mut auction = self.auctions.setter(auction_id);
+        auction.seller.set(seller);
+        auction.nft.set(nft);
+        auction.token_id.set(token_id);
+        auction.reserve_price.set(reserve_price);
+        auction.min_increment_bps.set(min_increment_bps);
+        auction.end_at.set(end_at);
+
+        // Log the create event.
+        evm::log(AuctionCreated {
+            auctionId: auction_id,
+            seller,
+            nft,
+            tokenId: token_id,
+            reservePrice: reserve_price,
+            endAt: end_at,
+        });
+        Ok(auction_id)
+    }
+
+    // Transfers an NFT held by the auction house.
+    fn transfer_nft(&mut self, nft: Address, to: Address, token_id: U256) -> Result<(), AuctionHouseError> {
+        let result = IERC721::new(nft).transfer_from(Call::new_in(self), contract::address(), to, token_id);
+        if let Err(err) = result {
+            let reason: Vec<u8> = err.into();
+            return Err(AuctionHouseError::NftTransferFailed(NftTransferFailed{reason: reason.into()}));
+        }
+        Ok(())
+    }
+
+    // Sends ETH, or lets `to` withdraw it later if the transfer fails, so a reverting receiver cannot block settlement.
+    fn pay_out(&mut self, to: Address, amount: U256) {
+        if amount == U256::ZERO {
+            return;
+        }
+        if transfer_eth(to, amount).is_err() {
+            let mut pending = self.pending_returns.setter(to);
+            let current = pending.get();
+            pending.set(current + amount);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+// Allow `cargo stylus export-abi` to generate a main function.
+#![cfg_attr(not(feature = "export-abi"), no_main)]
+extern crate alloc;
+
+/// Import items from the SDK. The prelude contains common traits and macros.
+use stylus_sdk::{alloy_primitives::{Address, U256, U8}, block, call::{transfer_eth, Call}, contract, evm, msg, prelude::*};
+use alloy_sol_types::sol;
+
+// Import the IERC721 interface.
+sol_interface! {
+    interface IERC721 {
+        // Required methods.
+        function safeTransferFrom(address from, address to, uint256 token_id) external;
+        function transferFrom(address, address, uint256) external;
+    }
+}
+
+// Define the events and errors for the contract.
+sol!{
+    // Define the events for the contract.
+    event Start(); // Start the auction.
+    event Buy(address indexed buyer, uint256 price); // The NFT was bought at `price`.
+    event Withdraw(address indexed seller, uint256 amount); // The seller withdrew the proceeds.
+    event Cancel(); // The seller cancelled the auction.
+
+    // Define the errors for the contract.
+    error AlreadyInitialized(); // The contract has already been initialized.
+    error AlreadyStarted(); // The auction has already started.
+    error NotSeller(); // The sender is not the seller.
+    error NotStarted(); // The auction has not started.
+    error AlreadySold(); // The NFT has already been sold.
+    error AuctionCancelled(); // The seller has cancelled the auction.
+    error InvalidPrices(); // The floor price is above the start price.
+    error InvalidDuration(); // The duration of the auction is zero.
+    error InvalidDecay(); // The decay mode is neither linear nor exponential.
+    error PaymentTooLow(uint256 price); // The payment is below the current price.
+    error NftTransferFailed(bytes reason); // A transfer of the NFT failed.
+    error TransferFailed(bytes reason); // A transfer of ETH failed.
+}
+
+#[derive(SolidityError)]
+pub enum DutchAuctionError {
+    // Define the errors for the contract.
+    AlreadyInitialized(AlreadyInitialized),
+    AlreadyStarted(AlreadyStarted),
+    NotSeller(NotSeller),
+    NotStarted(NotStarted),
+    AlreadySold(AlreadySold),
+    AuctionCancelled(AuctionCancelled),
+    InvalidPrices(InvalidPrices),
+    InvalidDuration(InvalidDuration),
+    InvalidDecay(InvalidDecay),
+    PaymentTooLow(PaymentTooLow),
+    NftTransferFailed(NftTransferFailed),
+    TransferFailed(TransferFailed),
+}
+
+// How the price falls from the start price to the floor price.
+#[repr(u8)]
+pub enum Decay {
+    // The price falls by the same amount every second.
+    Linear,
+    // The amount above the floor price halves `HALVINGS` times over the duration.
+    Exponential,
+}
+
+// Define some persistent storage using the Solidity ABI.
+// `DutchAuction` will be the entrypoint.
+sol_storage! {
+    #[entrypoint]
+    pub struct DutchAuction {
+        address nft_address; // The address of the NFT contract.
+        uint256 nft_id; // The ID of the NFT.
+
+        address seller; // The address of the seller.
+        uint256 start_price; // The price when the auction starts.
+        uint256 floor_price; // The price the auction falls to.
+        uint256 duration; // The time it takes the price to fall to the floor price.
+        uint8 decay; // How the price falls, see `Decay`.
+        uint256 start_at; // The start time of the auction.
+        bool started; // The auction has started or not.
+        bool sold; // The NFT has been sold or not.
+        bool cancelled; // The auction has been cancelled or not.
+        uint256 proceeds; // The ETH paid for the NFT, for the seller to withdraw.
+    }
+}
+
+/// Declare that `DutchAuction` is a contract with the following external methods.
+#[public]
+impl DutchAuction {
+    pub const HALVINGS: u64 = 10; // The number of times an exponential auction halves its price above the floor.
+
+    // Get nft address
+    pub fn nft(&self) -> Result<Address, DutchAuctionError> {
+        Ok(self.nft_address.get())
+    }
+
+    // Get nft id
+    pub fn nft_id(&self) -> Result<U256, DutchAuctionError> {
+        Ok(self.nft_id.get())
+    }
+
+    // Get seller address
+    pub fn seller(&self) -> Result<Address, DutchAuctionError> {
+        Ok(self.seller.get())
+    }
+
+    // Get the start price
+    pub fn start_price(&self) -> Result<U256, DutchAuctionError> {
+        Ok(self.start_price.get())
+    }
+
+    // Get the floor price
+    pub fn floor_price(&self) -> Result<U256, DutchAuctionError> {
+        Ok(self.floor_price.get())
+    }
+
+    // Get the duration of the auction
+    pub fn duration(&self) -> Result<U256, DutchAuctionError> {
+        Ok(self.duration.get())
+    }
+
+    // Get the decay mode, 0 for linear and 1 for exponential
+    pub fn decay(&self) -> Result<u8, DutchAuctionError> {
+        Ok(self.decay.get().to::<u8>())
+    }
+
+    // Get start time
+    pub fn start_at(&self) -> Result<U256, DutchAuctionError> {
+        Ok(self.start_at.get())
+    }
+
+    // Get started status
+    pub fn started(&self) -> Result<bool, DutchAuctionError> {
+        Ok(self.started.get())
+    }
+
+    // Get sold status
+    pub fn sold(&self) -> Result<bool, DutchAuctionError> {
+        Ok(self.sold.get())
+    }
+
+    // Get cancelled status
+    pub fn cancelled(&self) -> Result<bool, DutchAuctionError> {
+        Ok(self.cancelled.get())
+    }
+
+    // Get the proceeds the seller can withdraw
+    pub fn proceeds(&self) -> Result<U256, DutchAuctionError> {
+        Ok(self.proceeds.get())
+    }
+
+    // Get the price the NFT can be bought at right now
+    pub fn current_price(&self) -> Result<U256, DutchAuctionError> {
+        // Check if the auction has started.
+        if !self.started.get() {
+            return Err(DutchAuctionError::NotStarted(NotStarted{}));
+        }
+
+        let floor_price = self.floor_price.get();
+        let duration = self.duration.get();
+        let elapsed = U256::from(block::timestamp()) - self.start_at.get();
+        // The price stays at the floor price once the duration is over.
+        if elapsed >= duration {
+            return Ok(floor_price);
+        }
+
+        // Only the amount above the floor price decays.
+        let premium = self.start_price.get() - floor_price;
+        let premium = if self.decay.get().to::<u8>() == Decay::Linear as u8 {
+            // Fall by the same amount every second.
+            premium * (duration - elapsed) / duration
+        } else {
+            // Halve the premium `HALVINGS` times over the duration, and fall linearly within each halving.
+            let scaled = elapsed * U256::from(Self::HALVINGS);
+            let halvings = (scaled / duration).to::<usize>();
+            let premium = premium >> halvings;
+            premium - (premium / U256::from(2)) * (scaled % duration) / duration
+        };
+        Ok(floor_price + premium)
+    }
+
+    // Initialize program
+    pub fn initialize(
+        &mut self,
+        nft: Address,
+        nft_id: U256,
+        start_price: U256,
+        floor_price: U256,
+        duration: U256,
+        decay: u8,
+    ) -> Result<(), DutchAuctionError> {
+        // Check if the contract has already been initialized.
+        if self.seller.get() != Address::default() {
+            // Return an error if the contract has already been initialized.
+            return Err(DutchAuctionError::AlreadyInitialized(AlreadyInitialized{}));
+        }
+
+        // Check that the price falls, or stays the same.
+        if floor_price > start_price {
+            return Err(DutchAuctionError::InvalidPrices(InvalidPrices{}));
+        }
+
+        // Check that the auction will run for some time.
+        if duration == U256::ZERO {
+            return Err(DutchAuctionError::InvalidDuration(InvalidDuration{}));
+        }
+
+        // Check that the decay mode is known.
+        if decay > Decay::Exponential as u8 {
+            return Err(DutchAuctionError::InvalidDecay(InvalidDecay{}));
+        }
+
+        // Initialize the contract with the NFT address, the NFT ID, the seller, and the price curve.
+        self.nft_address.set(nft);
+        self.nft_id.set(nft_id);
+        self.seller.set(msg::sender());
+        self.start_price.set(start_price);
+        self.floor_price.set(floor_price);
+        self.duration.set(duration);
+        self.decay.set(U8::from(decay));
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<(), DutchAuctionError> {
+        // Check if the auction has already started.
+        if self.started.get() {
+            return Err(DutchAuctionError::AlreadyStarted(AlreadyStarted{}));
+        }
+
+        // Check if the sender is the seller.
+        if self.seller.get() != msg::sender() {
+            // Return an error if the sender is not the seller.
+            return Err(DutchAuctionError::NotSeller(NotSeller{}));
+        }
+
+        // Create a new instance of the IERC721 interface.
+        let nft = IERC721::new(*self.nft_address);
+        // Get the NFT ID.
+        let nft_id = self.nft_id.get();
+
+        // Transfer the NFT to the contract.
+        let config = Call::new_in(self);
+        if let Err(err) = nft.transfer_from(config, msg::sender(), contract::address(), nft_id) {
+            let reason: Vec<u8> = err.into();
+            return Err(DutchAuctionError::NftTransferFailed(NftTransferFailed{reason: reason.into()}));
+        }
+
+        // Start the price decay from now.
+        self.started.set(true);
+        self.start_at.set(U256::from(block::timestamp()));
+        // Log the start event.
+        evm::log(Start {});
+        Ok(())
+    }
+
+    // The cancel method allows the seller to cancel the auction while the NFT is unsold, returning the NFT.
+    pub fn cancel(&mut self) -> Result<(), DutchAuctionError> {
+        // Check if the sender is the seller.
+        if self.seller.get() != msg::sender() {
+            return Err(DutchAuctionError::NotSeller(NotSeller{}));
+        }
+
+        // Check if the auction has started.
+        if !self.started.get() {
+            return Err(DutchAuctionError::NotStarted(NotStarted{}));
+        }
+
+        // Check if the NFT is still for sale.
+        if self.sold.get() {
+            return Err(DutchAuctionError::AlreadySold(AlreadySold{}));
+        }
+        if self.cancelled.get() {
+            return Err(DutchAuctionError::AuctionCancelled(AuctionCancelled{}));
+        }
+
+        // Cancel the auction and transfer the NFT back to the seller.
+        self.cancelled.set(true);
+        let nft = IERC721::new(*self.nft_address);
+        let nft_id = self.nft_id.get();
+        let config = Call::new_in(self);
+        if let Err(err) = nft.transfer_from(config, contract::address(), msg::sender(), nft_id) {
+            let reason: Vec<u8> = err.into();
+            return Err(DutchAuctionError::NftTransferFailed(NftTransferFailed{reason: reason.into()}));
+        }
+
+        // Log the cancel event.
+        evm::log(Cancel {});
+        Ok(())
+    }
+
+    // The buy method lets the first buyer to pay the current price win the NFT.
+    // Any payment above the current price is refunded.
+    #[payable]
+    pub fn buy(&mut self) -> Result<(), DutchAuctionError> {
+        // Check if the NFT is still for sale.
+        if self.sold.get() {
+            return Err(DutchAuctionError::AlreadySold(AlreadySold{}));
+        }
+        if self.cancelled.get() {
+            return Err(DutchAuctionError::AuctionCancelled(AuctionCancelled{}));
+        }
+
+        // Check if the payment covers the current price.
+        let price = self.current_price()?;
+        if msg::value() < price {
+            return Err(DutchAuctionError::PaymentTooLow(PaymentTooLow{price}));
+        }
+        self.sold.set(true);
+
+        // Transfer the NFT to the buyer.
+        let nft = IERC721::new(*self.nft_address);
+        let nft_id = self.nft_id.get();
+        let config = Call::new_in(self);
+        if let Err(err) = nft.safe_transfer_from(config, contract::address(), msg::sender(), nft_id) {
+            let reason: Vec<u8> = err.into();
+            return Err(DutchAuctionError::NftTransferFailed(NftTransferFailed{reason: reason.into()}));
+        }
+
+        // Credit the price to the seller, who withdraws it, so that a seller that rejects ETH cannot block the sale.
+        // Refund the excess to the buyer.
+        self.proceeds.set(self.proceeds.get() + price);
+        pay(msg::sender(), msg::value() - price)?;
+
+        // Log the buy event.
+        evm::log(Buy {
+            buyer: msg::sender(),
+            price,
+        });
+        Ok(())
+    }
+
+    // The withdraw method sends the proceeds of the sale to the seller.
+    pub fn withdraw(&mut self) -> Result<(), DutchAuctionError> {
+        // Check if the sender is the seller.
+        if self.seller.get() != msg::sender() {
+            return Err(DutchAuctionError::NotSeller(NotSeller{}));
+        }
+
+        // Set the proceeds to 0 and transfer them to the seller.
+        let amount = self.proceeds.get();
+        self.proceeds.set(U256::ZERO);
+        pay(msg::sender(), amount)?;
+
+        // Log the withdraw event.
+        evm::log(Withdraw {
+            seller: msg::sender(),
+            amount,
+        });
+        Ok(())
+    }
+}
+
+// Sends `amount` of ETH to `to`, skipping empty transfers.
+fn pay(to: Address, amount: U256) -> Result<(), DutchAuctionError> {
+    if amount == U256::ZERO {
+        return Ok(());
+    }
+    transfer_eth(to, amount).map_err(|reason| DutchAuctionError::TransferFailed(TransferFailed{reason: reason.into()}))
+}
@@ -5,18 +5,25 @@ extern crate alloc;
 use std::borrow::BorrowMut;
 
 /// Import items from the SDK. The prelude contains common traits and macros.
-use stylus_sdk::{alloy_primitives::{Address, U256}, block, call::{transfer_eth, Call}, contract, evm, msg, prelude::*};
+use stylus_sdk::{alloy_primitives::{Address, U256}, block, call::{self, transfer_eth, Call}, contract, evm, msg, prelude::*};
 use alloy_sol_types::sol;
 
 // Import the IERC721 interface.
 sol_interface! {
     interface IERC721 {
         // Required methods.
-        function safeTransferFrom(address from, address to, uint256 token_id) external;
         function transferFrom(address, address, uint256) external;
     }
 }
 
+// Import the IERC20 interface, for auctions paid in an ERC-20 token.
+sol_interface! {
+    interface IERC20 {
+        function transfer(address to, uint256 value) external returns (bool);
+        function transferFrom(address from, address to, uint256 value) external returns (bool);
+    }
+}
+
 // Define the events and errors for the contract.
 sol!{
     // Define the events for the contract.
@@ -24,6 +31,8 @@ sol!{
     event Bid(address indexed sender, uint256 amount); // Bid on the auction.
     event Withdraw(address indexed bidder, uint256 amount); // Withdraw a bid.
     event End(address winner, uint256 amount); // End the auction.
+    event AuctionExtended(uint256 endAt); // A late bid pushed out the end of the auction.
+    event Cancel(); // The seller cancelled the auction.
 
     // Define the errors for the contract.
     error AlreadyInitialized(); // The contract has already been initialized.
@@ -33,6 +42,12 @@ sol!{
     error BidTooLow(); // The bid is too low.
     error NotStarted(); // The auction has not started.
     error NotEnded(); // The auction has not ended.
+    error InvalidDuration(); // The duration of the auction is zero.
+    error InvalidPaymentMethod(); // The bid was paid in ETH for a token auction or the other way round.
+    error TokenTransferFailed(address token, bytes reason); // A transfer of the payment token failed.
+    error HasBids(); // The auction cannot be cancelled after the first bid.
+    error NftTransferFailed(bytes reason); // A transfer of the NFT failed.
+    error TransferFailed(bytes reason); // A transfer of ETH failed.
 }
 
 
@@ -46,6 +61,12 @@ pub enum EnglishAuctionError {
     BidTooLow(BidTooLow),
     NotStarted(NotStarted),
     NotEnded(NotEnded),
+    InvalidDuration(InvalidDuration),
+    InvalidPaymentMethod(InvalidPaymentMethod),
+    TokenTransferFailed(TokenTransferFailed),
+    HasBids(HasBids),
+    NftTransferFailed(NftTransferFailed),
+    TransferFailed(TransferFailed),
 }
 
 // Define some persistent storage using the Solidity ABI.
@@ -55,11 +76,18 @@ sol_storage! {
     pub struct EnglishAuction {
         address nft_address; // The address of the NFT contract.
         uint256 nft_id; // The ID of the NFT.
+        address payment_token; // The ERC-20 token bids are paid in, or the zero address for ETH.
 
         address seller; // The address of the seller.
         uint256 end_at; // The end time of the auction.
         bool started; // The auction has started or not.
         bool ended; // The auction has ended or not.
+        uint256 duration; // The duration of the auction once started.
+        uint256 reserve_price; // The auction only sells the NFT if the highest bid reaches this price.
+        uint256 min_increment; // A bid must exceed the highest bid by at least this amount.
+        uint256 min_increment_bps; // A bid must exceed the highest bid by at least this many basis points of it.
+        uint256 extension_window; // A bid placed this many seconds before the end extends the auction.
+        uint256 extension_duration; // The auction is extended to end this many seconds after such a bid.
 
         address highest_bidder; // The address of the highest bidder.
         uint256 highest_bid; // The highest bid.
@@ -70,7 +98,7 @@ sol_storage! {
 /// Declare that `Counter` is a contract with the following external methods.
 #[public]
 impl EnglishAuction {
-    pub const ONE_DAY: u64 = 86400; // 1 day = 24 hours * 60 minutes * 60 seconds = 86400 seconds.
+    pub const BPS_DENOMINATOR: u64 = 10_000; // 100% = 10,000 basis points.
     
     // Get nft address
     pub fn nft(&self) -> Result<Address, EnglishAuctionError> {
@@ -81,6 +109,12 @@ impl EnglishAuction {
     pub fn nft_id(&self) -> Result<U256, EnglishAuctionError> {
         Ok(self.nft_id.get())
     }
+
+    // Get the ERC-20 token bids are paid in, the zero address for ETH
+    pub fn payment_token(&self) -> Result<Address, EnglishAuctionError> {
+        Ok(self.payment_token.get())
+    }
+
     // Get seller address
     pub fn seller(&self) -> Result<Address, EnglishAuctionError> {
         Ok(self.seller.get())
@@ -101,6 +135,49 @@ impl EnglishAuction {
         Ok(self.ended.get())
     }
 
+    // Get the duration of the auction
+    pub fn duration(&self) -> Result<U256, EnglishAuctionError> {
+        Ok(self.duration.get())
+    }
+
+    // Get the reserve price
+    pub fn reserve_price(&self) -> Result<U256, EnglishAuctionError> {
+        Ok(self.reserve_price.get())
+    }
+
+    // Get the minimum increment in absolute terms
+    pub fn min_increment(&self) -> Result<U256, EnglishAuctionError> {
+        Ok(self.min_increment.get())
+    }
+
+    // Get the minimum increment in basis points of the highest bid
+    pub fn min_increment_bps(&self) -> Result<U256, EnglishAuctionError> {
+        Ok(self.min_increment_bps.get())
+    }
+
+    // Get the lowest amount the next bid can be
+    pub fn min_next_bid(&self) -> Result<U256, EnglishAuctionError> {
+        let highest_bid = self.highest_bid.get();
+        // The first bid only has to exceed the starting bid.
+        if self.highest_bidder.get() == Address::default() {
+            return Ok(highest_bid + U256::from(1));
+        }
+        // Later bids have to exceed the highest bid by the larger of the two increments.
+        let bps_increment = highest_bid.saturating_mul(self.min_increment_bps.get()) / U256::from(Self::BPS_DENOMINATOR);
+        let increment = self.min_increment.get().max(bps_increment).max(U256::from(1));
+        Ok(highest_bid.saturating_add(increment))
+    }
+
+    // Get the time before the end during which a bid extends the auction
+    pub fn extension_window(&self) -> Result<U256, EnglishAuctionError> {
+        Ok(self.extension_window.get())
+    }
+
+    // Get the time the auction is extended to after a late bid
+    pub fn extension_duration(&self) -> Result<U256, EnglishAuctionError> {
+        Ok(self.extension_duration.get())
+    }
+
     // Get highest bidder address
     pub fn highest_bidder(&self) -> Result<Address, EnglishAuctionError> {
         Ok(self.highest_bidder.get())
@@ -117,18 +194,46 @@ impl EnglishAuction {
     }
 
     // Initialize program
-    pub fn initialize(&mut self, nft: Address, nft_id: U256, starting_bid: U256) -> Result<(), EnglishAuctionError> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize(
+        &mut self,
+        nft: Address,
+        nft_id: U256,
+        payment_token: Address,
+        starting_bid: U256,
+        reserve_price: U256,
+        min_increment: U256,
+        min_increment_bps: U256,
+        duration: U256,
+        extension_window: U256,
+        extension_duration: U256,
+    ) -> Result<(), EnglishAuctionError> {
         // Check if the contract has already been initialized.
         if self.seller.get() != Address::default() {
             // Return an error if the contract has already been initialized.
             return Err(EnglishAuctionError::AlreadyInitialized(AlreadyInitialized{}));
         }
+
+        // Check that the auction will run for some time.
+        if duration == U256::ZERO {
+            return Err(EnglishAuctionError::InvalidDuration(InvalidDuration{}));
+        }
         
         // Initialize the contract with the NFT address, the NFT ID, the seller, and the starting bid.
         self.nft_address.set(nft);
         self.nft_id.set(nft_id);
+        // Bids are paid in ETH if no payment token is given.
+        self.payment_token.set(payment_token);
         self.seller.set(msg::sender());
         self.highest_bid.set(starting_bid);
+        self.reserve_price.set(reserve_price);
+        // Bids have to exceed the highest bid by the larger of `min_increment` and `min_increment_bps` basis points of it.
+        self.min_increment.set(min_increment);
+        self.min_increment_bps.set(min_increment_bps);
+        self.duration.set(duration);
+        // Bids placed within `extension_window` seconds of the end push it out to `extension_duration` seconds after the bid.
+        self.extension_window.set(extension_window);
+        self.extension_duration.set(extension_duration);
         Ok(())
     }
 
@@ -152,73 +257,85 @@ impl EnglishAuction {
         // Transfer the NFT to the contract.
         let config = Call::new_in(self);
         let result = nft.transfer_from(config, msg::sender(), contract::address(), nft_id);
-        
-        match result {
-            // If the transfer is successful, start the auction.
-            Ok(_) => {
-                self.started.set(true);
-                // Set the end time of the auction to 7 days from now.
-                self.end_at.set(U256::from(block::timestamp() + 7 * Self::ONE_DAY));
-                // Log the start event.
-                evm::log(Start {});
-                Ok(())
-            },
-            // If the transfer fails, return an error.
-            Err(_) => {
-                return Err(EnglishAuctionError::NotSeller(NotSeller{}));
-            }
-            
-        }
+        check_nft_transfer(result)?;
+
+        // Start the auction.
+        self.started.set(true);
+        // Set the end time of the auction to `duration` seconds from now.
+        self.end_at.set(U256::from(block::timestamp()) + self.duration.get());
+        // Log the start event.
+        evm::log(Start {});
+        Ok(())
     }
 
-    // The bid method allows bidders to place a bid on the auction.
-    #[payable]
-    pub fn bid(&mut self) -> Result<(), EnglishAuctionError> {
+    // The cancel method allows the seller to cancel the auction before the first bid, returning the NFT.
+    pub fn cancel(&mut self) -> Result<(), EnglishAuctionError> {
+        // Check if the sender is the seller.
+        if self.seller.get() != msg::sender() {
+            return Err(EnglishAuctionError::NotSeller(NotSeller{}));
+        }
+
         // Check if the auction has started.
         if !self.started.get() {
-            // Return an error if the auction has not started.
-            return Err(EnglishAuctionError::NotSeller(NotSeller{}));
+            return Err(EnglishAuctionError::NotStarted(NotStarted{}));
         }
-        
-        // Check if the auction has ended.
-        if U256::from(block::timestamp()) >= self.end_at.get() {
-            // Return an error if the auction has ended.
+
+        // Check if the auction has already ended.
+        if self.ended.get() {
             return Err(EnglishAuctionError::AuctionEnded(AuctionEnded{}));
         }
-        
-        // Check if the bid amount is higher than the current highest bid.
-        if msg::value() <= self.highest_bid.get() {
-            // Return an error if the bid amount is too low.
-            return Err(EnglishAuctionError::BidTooLow(BidTooLow{}));
-        }
-        
-        // Refund the previous highest bidder. (But will not transfer back at this call, needs bidders to call withdraw() to get back the fund.
+
+        // Check if there are any bids.
         if self.highest_bidder.get() != Address::default() {
-            let mut bid = self.bids.setter(self.highest_bidder.get());
-            let current_bid = bid.get();
-            bid.set(current_bid + self.highest_bid.get());
+            return Err(EnglishAuctionError::HasBids(HasBids{}));
         }
-        
-        // Update the highest bidder and the highest bid.
-        self.highest_bidder.set(msg::sender());
-        self.highest_bid.set(msg::value());
 
-        // Update the bid of the current bidder.
-        evm::log(Bid {
-            sender: msg::sender(),
-            amount: msg::value(),
-        });
+        // End the auction and transfer the NFT back to the seller.
+        self.ended.set(true);
+        let nft = IERC721::new(*self.nft_address);
+        let nft_id = self.nft_id.get();
+        let config = Call::new_in(self);
+        let result = nft.transfer_from(config, contract::address(), msg::sender(), nft_id);
+        check_nft_transfer(result)?;
+
+        // Log the cancel event.
+        evm::log(Cancel {});
         Ok(())
     }
 
-    // The withdraw method allows bidders to withdraw their bid.
+    // The bid method allows bidders to place a bid on the auction, paid in ETH.
+    #[payable]
+    pub fn bid(&mut self) -> Result<(), EnglishAuctionError> {
+        // Check if the auction is paid in ETH.
+        if self.payment_token.get() != Address::default() {
+            return Err(EnglishAuctionError::InvalidPaymentMethod(InvalidPaymentMethod{}));
+        }
+        self.place_bid(msg::value())
+    }
+
+    // The bid_with_token method allows bidders to place a bid on an auction paid in an ERC-20 token.
+    // The bidder has to approve the auction to spend `amount` first.
+    pub fn bid_with_token(&mut self, amount: U256) -> Result<(), EnglishAuctionError> {
+        // Check if the auction is paid in a token.
+        let token = self.payment_token.get();
+        if token == Address::default() {
+            return Err(EnglishAuctionError::InvalidPaymentMethod(InvalidPaymentMethod{}));
+        }
+        self.place_bid(amount)?;
+
+        // Pull the bid from the bidder.
+        let result = IERC20::new(token).transfer_from(Call::new_in(self), msg::sender(), contract::address(), amount);
+        check_token_transfer(token, result)
+    }
+
+    // The withdraw method allows bidders to withdraw their bid, and the seller to withdraw a payout that could not be sent.
     pub fn withdraw(&mut self) -> Result<(), EnglishAuctionError> {
         // Get the current bid of the bidder.
         let mut current_bid = self.bids.setter(msg::sender());
         let bal = current_bid.get();
         // Set the record of this bidder to 0 and transfer back tokens.
         current_bid.set(U256::from(0));
-        let _ = transfer_eth(msg::sender(), bal);
+        self.pay(msg::sender(), bal)?;
 
         // Log the withdraw event.
         evm::log(Withdraw {
@@ -249,32 +366,140 @@ impl EnglishAuction {
         }
         
         // End the auction and transfer the NFT and the highest bid to the winner.
+        // The NFT is sent with `transferFrom`, so a winner that cannot receive NFTs safely cannot block the auction.
         storage.borrow_mut().ended.set(true);
         let nft_contract_address = *storage.borrow_mut().nft_address;
         let seller_address = storage.borrow_mut().seller.get();
         let highest_bid = storage.borrow_mut().highest_bid.get();
         let highest_bidder = storage.borrow_mut().highest_bidder.get();
         let nft_id = storage.borrow_mut().nft_id.get();
+        let reserve_met = highest_bid >= storage.borrow_mut().reserve_price.get();
+
+        // If the reserve price is not met, the highest bidder can withdraw their bid.
+        if highest_bidder != Address::default() && !reserve_met {
+            let mut bid = storage.borrow_mut().bids.setter(highest_bidder);
+            let current_bid = bid.get();
+            bid.set(current_bid + highest_bid);
+        }
         let config = Call::new_in(storage.borrow_mut());
         
         let nft = IERC721::new(nft_contract_address);
         
-        // Check if there is highest bidder.
-        if highest_bidder != Address::default() {
+        // Check if there is highest bidder who met the reserve price.
+        let sold = highest_bidder != Address::default() && reserve_met;
+        if sold {
             // If there is a highest bidder, transfer the NFT to the highest bidder.
-            let _ = nft.safe_transfer_from(config, contract::address(), highest_bidder, nft_id);
-            // Transfer the highest bid to the seller.
-            let _ = transfer_eth(seller_address, highest_bid);
+            let result = nft.transfer_from(config, contract::address(), highest_bidder, nft_id);
+            check_nft_transfer(result)?;
+            // Transfer the highest bid to the seller, or let the seller withdraw it if the transfer fails.
+            storage.borrow_mut().pay_or_credit(seller_address, highest_bid)?;
         } else {
-            // If there is no highest bidder, transfer the NFT back to the seller.
-            let _ = nft.safe_transfer_from(config, contract::address(), seller_address, nft_id);
+            // If there is no highest bidder or the reserve price is not met, transfer the NFT back to the seller.
+            let result = nft.transfer_from(config, contract::address(), seller_address, nft_id);
+            check_nft_transfer(result)?;
         }
 
-        // Log the end event.
+        // Log the end event, with no winner if the NFT was not sold.
         evm::log(End {
-            winner: highest_bidder,
-            amount: highest_bid,
+            winner: if sold { highest_bidder } else { Address::default() },
+            amount: if sold { highest_bid } else { U256::ZERO },
         });
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// These methods aren't exposed to other contracts.
+impl EnglishAuction {
+    // Places a bid of `amount` for the sender, the payment is handled by the caller.
+    fn place_bid(&mut self, amount: U256) -> Result<(), EnglishAuctionError> {
+        // Check if the auction has started.
+        if !self.started.get() {
+            // Return an error if the auction has not started.
+            return Err(EnglishAuctionError::NotStarted(NotStarted{}));
+        }
+        
+        // Check if the auction has ended.
+        let now = U256::from(block::timestamp());
+        if now >= self.end_at.get() {
+            // Return an error if the auction has ended.
+            return Err(EnglishAuctionError::AuctionEnded(AuctionEnded{}));
+        }
+        
+        // Check if the bid amount exceeds the current highest bid by the minimum increment.
+        if amount < self.min_next_bid()? {
+            // Return an error if the bid amount is too low.
+            return Err(EnglishAuctionError::BidTooLow(BidTooLow{}));
+        }
+        
+        // Refund the previous highest bidder. (But will not transfer back at this call, needs bidders to call withdraw() to get back the fund.
+        if self.highest_bidder.get() != Address::default() {
+            let mut bid = self.bids.setter(self.highest_bidder.get());
+            let current_bid = bid.get();
+            bid.set(current_bid + self.highest_bid.get());
+        }
+        
+        // Update the highest bidder and the highest bid.
+        self.highest_bidder.set(msg::sender());
+        self.highest_bid.set(amount);
+
+        // Update the bid of the current bidder.
+        evm::log(Bid {
+            sender: msg::sender(),
+            amount,
+        });
+
+        // Extend the auction if the bid was placed within the extension window, so that it cannot be sniped.
+        if self.end_at.get() - now <= self.extension_window.get() {
+            let end_at = now + self.extension_duration.get();
+            if end_at > self.end_at.get() {
+                self.end_at.set(end_at);
+                evm::log(AuctionExtended { endAt: end_at });
+            }
+        }
+        Ok(())
+    }
+
+    // Sends `amount` of the payment currency to `to`.
+    fn pay(&mut self, to: Address, amount: U256) -> Result<(), EnglishAuctionError> {
+        let token = self.payment_token.get();
+        if token == Address::default() {
+            return transfer_eth(to, amount).map_err(|reason| EnglishAuctionError::TransferFailed(TransferFailed{reason: reason.into()}));
+        }
+        let result = IERC20::new(token).transfer(Call::new_in(self), to, amount);
+        check_token_transfer(token, result)
+    }
+
+    // Sends `amount` of the payment currency to `to`.
+    // If the ETH or token transfer fails, the amount is added to the balance `to` can withdraw instead.
+    fn pay_or_credit(&mut self, to: Address, amount: U256) -> Result<(), EnglishAuctionError> {
+        match self.pay(to, amount) {
+            Err(EnglishAuctionError::TransferFailed(_) | EnglishAuctionError::TokenTransferFailed(_)) => {
+                let mut balance = self.bids.setter(to);
+                let current_balance = balance.get();
+                balance.set(current_balance + amount);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+// Turns the result of an NFT transfer into an error carrying the revert data of the NFT contract.
+fn check_nft_transfer(result: Result<(), call::Error>) -> Result<(), EnglishAuctionError> {
+    result.map_err(|err| {
+        let reason: Vec<u8> = err.into();
+        EnglishAuctionError::NftTransferFailed(NftTransferFailed{reason: reason.into()})
+    })
+}
+
+// Turns the result of an ERC-20 transfer into an error if it reverted or returned false.
+fn check_token_transfer(token: Address, result: Result<bool, call::Error>) -> Result<(), EnglishAuctionError> {
+    match result {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(EnglishAuctionError::TokenTransferFailed(TokenTransferFailed{token, reason: Vec::new().into()})),
+        Err(err) => {
+            let reason: Vec<u8> = err.into();
+            Err(EnglishAuctionError::TokenTransferFailed(TokenTransferFailed{token, reason: reason.into()}))
+        }
+    }
+}
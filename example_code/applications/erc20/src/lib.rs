@@ -4,13 +4,16 @@ extern crate alloc;
 
 // Modules and imports
 mod erc20;
+mod multicall;
 
 use alloy_primitives::{Address, U256};
 use stylus_sdk::{
+    abi::Bytes,
     msg,
     prelude::*
 };
 use crate::erc20::{Erc20, Erc20Params, Erc20Error};
+use crate::multicall::MulticallError;
 
 /// Immutable definitions
 struct StylusTokenParams;
@@ -52,4 +55,9 @@ impl StylusToken {
         self.erc20.burn(msg::sender(), value)?;
         Ok(())
     }
+
+    /// Batches several calls to StylusToken in one transaction
+    pub fn multicall(&mut self, data: Vec<Bytes>) -> Result<Vec<Bytes>, MulticallError> {
+        multicall::multicall(self, data)
+    }
 }
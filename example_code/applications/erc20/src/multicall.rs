@@ -0,0 +1,121 @@
+//! Batching of a contract's own methods
+//!
+//! The [`multicall`] function runs each entry of a `multicall(bytes[])` batch
+//! through the router of the contract's entrypoint, and is intended to back
+//! a `multicall` method on other contract types.
+//!
+//! Every entry is dispatched in-process rather than through a call to the
+//! contract's own address, which the router would reject as reentrant, so
+//! `msg::sender()` is preserved and no external call is made. The batch
+//! reverts with the index and revert data of the first entry that fails.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+// Imported packages
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+use alloy_primitives::U256;
+use alloy_sol_types::sol;
+use stylus_sdk::{
+    abi::{Bytes, Router},
+    prelude::*,
+    storage::TopLevelStorage,
+};
+
+// Declare Solidity error types
+sol! {
+    error MulticallFailed(uint256 index, bytes reason);
+}
+
+/// Represents the ways methods may fail.
+#[derive(SolidityError)]
+pub enum MulticallError {
+    MulticallFailed(MulticallFailed),
+}
+
+/// Routes each entry of `data` in order through the router of `storage`, returning their return data.
+/// The calling method should not be payable, so that no entry can reuse the ETH sent with the batch.
+pub fn multicall<S>(storage: &mut S, data: Vec<Bytes>) -> Result<Vec<Bytes>, MulticallError>
+where
+    S: TopLevelStorage + Router<S> + BorrowMut<<S as Router<S>>::Storage>,
+{
+    let mut results = Vec::with_capacity(data.len());
+    for (index, calldata) in data.iter().enumerate() {
+        let failed = |reason: Vec<u8>| {
+            MulticallError::MulticallFailed(MulticallFailed {
+                index: U256::from(index),
+                reason: reason.into(),
+            })
+        };
+
+        // Entries without a known selector fail like unknown methods do
+        let Some(selector) = calldata.get(..4) else {
+            return Err(failed(Vec::new()));
+        };
+        let selector = u32::from_be_bytes(selector.try_into().unwrap());
+        let result = S::route(storage, selector, &calldata[4..])
+            .unwrap_or_else(|| Err(Vec::new()))
+            .map_err(failed)?;
+        results.push(result.into());
+    }
+    Ok(results)
+}
+
+// Outside of the Stylus VM, the hostios are only available without the `export-abi` feature,
+// which replaces them with stubs.
+#[cfg(all(test, not(feature = "export-abi")))]
+mod test {
+    use super::*;
+    use alloy_primitives::{address, Address};
+    use stylus_sdk::{msg, ArbResult};
+
+    const SENDER: Address = address!("00000000000000000000000000000000000000aa");
+
+    // Stands in for the `msg_sender` hostio of the Stylus VM.
+    #[no_mangle]
+    unsafe extern "C" fn msg_sender(sender: *mut u8) {
+        core::ptr::copy(SENDER.as_ptr(), sender, 20);
+    }
+
+    // A contract whose router returns the sender for selector 1 and reverts with its input for selector 2.
+    struct Contract;
+
+    unsafe impl TopLevelStorage for Contract {}
+
+    impl Router<Contract> for Contract {
+        type Storage = Contract;
+
+        fn route(_storage: &mut Contract, selector: u32, input: &[u8]) -> Option<ArbResult> {
+            match selector {
+                1 => Some(Ok(msg::sender().to_vec())),
+                2 => Some(Err(input.to_vec())),
+                _ => None,
+            }
+        }
+    }
+
+    fn failed_at(result: Result<Vec<Bytes>, MulticallError>) -> (U256, Vec<u8>) {
+        match result {
+            Err(MulticallError::MulticallFailed(MulticallFailed { index, reason })) => (index, reason.to_vec()),
+            Ok(_) => panic!("the batch should fail"),
+        }
+    }
+
+    #[test]
+    fn test_multicall() {
+        // Every entry sees the sender of the batch
+        let Ok(results) = multicall(&mut Contract, vec![vec![0, 0, 0, 1].into(), vec![0, 0, 0, 1].into()]) else {
+            panic!("the batch should succeed");
+        };
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.to_vec() == SENDER.to_vec()));
+
+        // The batch fails with the index and the revert data of the first entry that fails
+        let data = vec![vec![0, 0, 0, 1].into(), vec![0, 0, 0, 2, 0xde, 0xad].into(), vec![0, 0, 0, 2, 0xbe, 0xef].into()];
+        assert_eq!(failed_at(multicall(&mut Contract, data)), (U256::from(1), vec![0xde, 0xad]));
+
+        // Entries that are too short or have an unknown selector fail without revert data
+        assert_eq!(failed_at(multicall(&mut Contract, vec![vec![0, 0, 0, 1].into(), vec![0, 0].into()])), (U256::from(1), vec![]));
+        assert_eq!(failed_at(multicall(&mut Contract, vec![vec![0, 0, 0, 3].into()])), (U256::ZERO, vec![]));
+    }
+}
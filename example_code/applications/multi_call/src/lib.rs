@@ -3,22 +3,42 @@ extern crate alloc;
 
 use alloy_primitives::U256;
 use alloy_sol_types::sol;
-use stylus_sdk::{abi::Bytes, alloy_primitives::Address, call::RawCall, prelude::*};
+use stylus_sdk::{abi::Bytes, alloy_primitives::{address, Address, B256}, block, call::{self, RawCall}, msg, prelude::*, types::AddressVM};
 
 #[storage]
 #[entrypoint]
 pub struct MultiCall;
 
+// The Multicall3 call types: `Call(target, callData)`, `Call3(target, allowFailure, callData)`
+// and `Call3Value(target, allowFailure, value, callData)`.
+pub type Call = (Address, Bytes);
+pub type Call3 = (Address, bool, Bytes);
+pub type Call3Value = (Address, bool, U256, Bytes);
+// The Multicall3 result type: `Result(success, returnData)`.
+pub type CallResult = (bool, Bytes);
+
+// Stylus has no host function for block hashes, so they are read from the ArbSys precompile.
+sol_interface! {
+    interface IArbSys {
+        function arbBlockNumber() external view returns (uint256);
+        function arbBlockHash(uint256 arb_block_num) external view returns (bytes32);
+    }
+}
+
+const ARB_SYS: Address = address!("0000000000000000000000000000000000000064");
+
 // Declare events and Solidity error types
 sol! {
     error ArraySizeNotMatch();
     error CallFailed(uint256 call_index);
+    error ValueMismatch(uint256 expected, uint256 received);
 }
 
 #[derive(SolidityError)]
 pub enum MultiCallErrors {
     ArraySizeNotMatch(ArraySizeNotMatch),
     CallFailed(CallFailed),
+    ValueMismatch(ValueMismatch),
 }
 
 #[public]
@@ -41,4 +61,123 @@ impl MultiCall {
 }
         Ok(results)
     }
-}
\ No newline at end of file
+
+    // Makes every call and reverts if any of them fails, returning the block number and the return data.
+    #[payable]
+    pub fn aggregate(&mut self, calls: Vec<Call>) -> Result<(U256, Vec<Bytes>), MultiCallErrors> {
+        let results = self.try_aggregate(true, calls)?;
+        Ok((U256::from(block::number()), results.into_iter().map(|(_, data)| data).collect()))
+    }
+
+    // Makes every call, and reverts if any of them fails when `require_success` is set.
+    #[payable]
+    pub fn try_aggregate(&mut self, require_success: bool, calls: Vec<Call>) -> Result<Vec<CallResult>, MultiCallErrors> {
+        let mut results = Vec::with_capacity(calls.len());
+        for (i, (target, data)) in calls.into_iter().enumerate() {
+            results.push(call(i, target, !require_success, U256::ZERO, data)?);
+        }
+        Ok(results)
+    }
+
+    // Same as `try_aggregate`, also returning the block number and block hash.
+    // The hash of the current block is not known yet, so it is always zero, as in Multicall3.
+    #[payable]
+    pub fn try_block_and_aggregate(&mut self, require_success: bool, calls: Vec<Call>) -> Result<(U256, B256, Vec<CallResult>), MultiCallErrors> {
+        let results = self.try_aggregate(require_success, calls)?;
+        Ok((U256::from(block::number()), B256::ZERO, results))
+    }
+
+    // Same as `try_block_and_aggregate`, reverting if any call fails.
+    #[payable]
+    pub fn block_and_aggregate(&mut self, calls: Vec<Call>) -> Result<(U256, B256, Vec<CallResult>), MultiCallErrors> {
+        self.try_block_and_aggregate(true, calls)
+    }
+
+    // Makes every call, and reverts if a call that does not allow failure fails.
+    #[payable]
+    pub fn aggregate3(&mut self, calls: Vec<Call3>) -> Result<Vec<CallResult>, MultiCallErrors> {
+        let mut results = Vec::with_capacity(calls.len());
+        for (i, (target, allow_failure, data)) in calls.into_iter().enumerate() {
+            results.push(call(i, target, allow_failure, U256::ZERO, data)?);
+        }
+        Ok(results)
+    }
+
+    // Same as `aggregate3`, sending `value` with every call. The values have to add up to the ETH sent.
+    #[payable]
+    pub fn aggregate3_value(&mut self, calls: Vec<Call3Value>) -> Result<Vec<CallResult>, MultiCallErrors> {
+        let mut total = U256::ZERO;
+        let mut results = Vec::with_capacity(calls.len());
+        for (i, (target, allow_failure, value, data)) in calls.into_iter().enumerate() {
+            total += value;
+            results.push(call(i, target, allow_failure, value, data)?);
+        }
+        if total != msg::value() {
+            return Err(MultiCallErrors::ValueMismatch(ValueMismatch { expected: total, received: msg::value() }));
+        }
+        Ok(results)
+    }
+
+    // Returns the block number.
+    pub fn get_block_number(&self) -> U256 {
+        U256::from(block::number())
+    }
+
+    // Returns the hash of an Arbitrum block, or zero if it is not one of the last 256 blocks.
+    // Unlike `getBlockNumber`, which returns `block.number` like Multicall3, `blockNumber` is an Arbitrum block number.
+    pub fn get_block_hash(&self, block_number: U256) -> B256 {
+        IArbSys::new(ARB_SYS).arb_block_hash(call::Call::new(), block_number).unwrap_or(B256::ZERO)
+    }
+
+    // Returns the hash of the previous Arbitrum block.
+    pub fn get_last_block_hash(&self) -> B256 {
+        match IArbSys::new(ARB_SYS).arb_block_number(call::Call::new()) {
+            Ok(number) if number > U256::ZERO => self.get_block_hash(number - U256::from(1)),
+            _ => B256::ZERO,
+        }
+    }
+
+    // Returns the block timestamp.
+    pub fn get_current_block_timestamp(&self) -> U256 {
+        U256::from(block::timestamp())
+    }
+
+    // Returns the block coinbase.
+    pub fn get_current_block_coinbase(&self) -> Address {
+        block::coinbase()
+    }
+
+    // Returns the block difficulty, which is always 1 on Arbitrum, as is `prevrandao`.
+    pub fn get_current_block_difficulty(&self) -> U256 {
+        U256::from(1)
+    }
+
+    // Returns the block gas limit.
+    pub fn get_current_block_gas_limit(&self) -> U256 {
+        U256::from(block::gas_limit())
+    }
+
+    // Returns the block base fee.
+    pub fn get_basefee(&self) -> U256 {
+        block::basefee()
+    }
+
+    // Returns the chain ID.
+    pub fn get_chain_id(&self) -> U256 {
+        U256::from(block::chainid())
+    }
+
+    // Returns the ETH balance of an address.
+    pub fn get_eth_balance(&self, addr: Address) -> U256 {
+        addr.balance()
+    }
+}
+
+// Makes the call at `index`, returning an error if it fails and failure is not allowed.
+fn call(index: usize, target: Address, allow_failure: bool, value: U256, data: Bytes) -> Result<CallResult, MultiCallErrors> {
+    match RawCall::new_with_value(value).call(target, &data) {
+        Ok(result) => Ok((true, result.into())),
+        Err(result) if allow_failure => Ok((false, result.into())),
+        Err(_) => Err(MultiCallErrors::CallFailed(CallFailed { call_index: U256::from(index) })),
+    }
+}
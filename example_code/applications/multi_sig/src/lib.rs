@@ -3,8 +3,18 @@
 extern crate alloc;
 
 /// Import items from the SDK. The prelude contains common traits and macros.
-use stylus_sdk::{contract, evm, msg, prelude::*, call::{Call, call}, alloy_primitives::{Address, U256}, abi::Bytes};
-use alloy_sol_types::sol;
+use stylus_sdk::{block, contract, evm, msg, prelude::*, call::{Call, call, static_call}, alloy_primitives::{address, Address, FixedBytes, U256}, abi::{AbiType, Bytes}, crypto::keccak, function_selector};
+use alloy_sol_types::{sol, SolType};
+
+const ECRECOVER: Address = address!("0000000000000000000000000000000000000001");
+// The EIP-712 types of the messages owners sign for `is_valid_signature`, which tie a signature to this wallet and chain.
+const DOMAIN_TYPE: &str = "EIP712Domain(uint256 chainId,address verifyingContract)";
+const MULTISIG_MESSAGE_TYPE: &str = "MultiSigMessage(bytes32 hash)";
+// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`, returned by `is_valid_signature` for valid signatures.
+const ERC1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+const ERC1271_INVALID_VALUE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+// The length of an ECDSA signature (r, s, v).
+const SIGNATURE_LENGTH: usize = 65;
 
 // Define some events using the Solidity ABI.
 sol! {
@@ -19,6 +29,11 @@ sol! {
     event ConfirmTransaction(address indexed owner, uint256 indexed txIndex);
     event RevokeConfirmation(address indexed owner, uint256 indexed txIndex);
     event ExecuteTransaction(address indexed owner, uint256 indexed txIndex);
+    event ExecutionFailure(address indexed owner, uint256 indexed txIndex, bytes reason);
+    event SetExpiry(uint256 indexed txIndex, uint256 expiresAt);
+    event ConfirmCancellation(address indexed owner, uint256 indexed txIndex);
+    event CancelTransaction(address indexed owner, uint256 indexed txIndex);
+    event SignMessage(bytes32 indexed msgHash);
 
     // Error types for the MultiSig contract
     error AlreadyInitialized();
@@ -32,7 +47,13 @@ sol! {
     error TxAlreadyConfirmed();
     error TxNotConfirmed();
     error ConfirmationNumberNotEnough();
-    error ExecuteFailed();
+    error ExecuteFailed(bytes reason); // Carries the revert data of the failed call.
+    error InvalidExpiry(); // The expiry timestamp is already in the past.
+    error TransactionExpired(uint256 txIndex, uint256 expiresAt);
+    error TransactionCancelled(uint256 txIndex);
+    error CancellationAlreadyConfirmed();
+    error NotWallet(); // The sender is not the wallet itself, i.e. the call did not go through a confirmed transaction.
+    error BatchCallFailed(uint256 index, bytes reason); // The call at `index` of a batch failed with `reason`.
 }
 
 // Define some persistent storage using the Solidity ABI.
@@ -46,15 +67,23 @@ sol_storage! {
         TxStruct[] transactions; // The transactions array
         // mapping from tx index => owner => bool
         mapping(uint256 => mapping(address => bool)) is_confirmed;
+        // mapping from tx index => owner => bool
+        mapping(uint256 => mapping(address => bool)) is_cancel_confirmed;
+        // mapping from message hash => whether the wallet has signed it
+        mapping(bytes32 => bool) signed_messages;
     }
 
     // Define the `TxStruct` struct
     pub struct TxStruct {
         address to;
         uint256 value;
-        bytes data;
+        bytes32[] data; // The calldata of the transaction, split into 32-byte words
+        uint256 data_length; // The length of the calldata in bytes
         bool executed; // Whether the transaction has been executed
         uint256 num_confirmations; // The number of confirmations of the current transaction
+        uint256 expires_at; // The timestamp after which the transaction can no longer be executed, 0 for no expiry
+        bool cancelled; // Whether the transaction has been cancelled by the owners
+        uint256 num_cancellations; // The number of owners that voted to cancel the current transaction
     }
 }
 
@@ -73,6 +102,12 @@ pub enum MultiSigError {
     TxNotConfirmed(TxNotConfirmed),
     ConfirmationNumberNotEnough(ConfirmationNumberNotEnough),
     ExecuteFailed(ExecuteFailed),
+    InvalidExpiry(InvalidExpiry),
+    TransactionExpired(TransactionExpired),
+    TransactionCancelled(TransactionCancelled),
+    CancellationAlreadyConfirmed(CancellationAlreadyConfirmed),
+    NotWallet(NotWallet),
+    BatchCallFailed(BatchCallFailed),
 }
 
 /// Declare that `MultiSig` is a contract with the following external methods.
@@ -89,50 +124,98 @@ impl MultiSig {
         let amount = msg::value();
         evm::log(
             Deposit{
-                sender: sender, 
-                amount: amount, 
+                sender,
+                amount,
                 balance: contract::balance()
             });
     }
 
     // The `submit_transaction` method submits a new transaction to the contract.
     pub fn submit_transaction(&mut self, to: Address, value: U256, data: Bytes) -> Result<(), MultiSigError> {
+        self.submit_transaction_with_expiry(to, value, data, U256::ZERO)
+    }
+
+    // The `submit_transaction_with_expiry` method submits a new transaction that can only be executed until `expires_at`.
+    // An `expires_at` of 0 means the transaction never expires.
+    pub fn submit_transaction_with_expiry(&mut self, to: Address, value: U256, data: Bytes, expires_at: U256) -> Result<(), MultiSigError> {
         // The sender must be an owner.
-        if !self.is_owner.get(msg::sender()) {
-            return Err(MultiSigError::NotOwner(NotOwner{}));
+        self.check_owner()?;
+
+        // The expiry must be in the future.
+        if expires_at != U256::ZERO && expires_at <= U256::from(block::timestamp()) {
+            return Err(MultiSigError::InvalidExpiry(InvalidExpiry{}));
         }
 
         let tx_index = U256::from(self.transactions.len());
-        
+
         // Add the transaction to the transactions array.
         let mut new_tx = self.transactions.grow();
         new_tx.to.set(to);
         new_tx.value.set(value);
-        new_tx.data.set_bytes(data.clone());
+        new_tx.data_length.set(U256::from(data.len()));
+        for chunk in data.chunks(32) {
+            let mut word = FixedBytes::<32>::ZERO;
+            word[..chunk.len()].copy_from_slice(chunk);
+            new_tx.data.push(word);
+        }
         new_tx.executed.set(false);
         new_tx.num_confirmations.set(U256::from(0));
+        new_tx.expires_at.set(expires_at);
+        new_tx.cancelled.set(false);
+        new_tx.num_cancellations.set(U256::from(0));
 
         // Emit the `SubmitTransaction` event.
         evm::log(SubmitTransaction {
             owner: msg::sender(),
             txIndex: tx_index,
-            to: to,
-            value: value,
+            to,
+            value,
             data: data.to_vec().into(),
         });
+
+        // Emit the `SetExpiry` event if the transaction can expire.
+        if expires_at != U256::ZERO {
+            evm::log(SetExpiry {
+                txIndex: tx_index,
+                expiresAt: expires_at,
+            });
+        }
         Ok(())
     }
 
+    // The `submit_batch` method submits a list of (to, value, data) calls as a single transaction.
+    // Once confirmed, the calls are executed atomically: if any of them fails, none of them takes effect.
+    pub fn submit_batch(&mut self, calls: Vec<(Address, U256, Bytes)>) -> Result<(), MultiSigError> {
+        // The batch is a transaction from the wallet to its own `execute_batch` method.
+        let selector = function_selector!("executeBatch", Vec<(Address, U256, Bytes)>);
+        let params = <(Vec<(Address, U256, Bytes)>,) as AbiType>::SolType::abi_encode_params(&(calls,));
+        let data = [&selector[..], &params].concat();
+        self.submit_transaction(contract::address(), U256::ZERO, data.into())
+    }
+
+    // The `execute_batch` method executes the calls of a batch in order, reverting if any of them fails.
+    // It can only be called by the wallet itself, when executing a transaction submitted with `submit_batch`.
+    pub fn execute_batch(&mut self, calls: Vec<(Address, U256, Bytes)>) -> Result<(), MultiSigError> {
+        self.check_wallet()?;
+
+        for (index, (to, value, data)) in calls.into_iter().enumerate() {
+            if let Err(err) = call(Call::new_in(self).value(value), to, &data) {
+                let reason: Vec<u8> = err.into();
+                return Err(MultiSigError::BatchCallFailed(BatchCallFailed{index: U256::from(index), reason: reason.into()}));
+            }
+        }
+        Ok(())
+    }
 
     // The `initialize` method initializes the contract with the owners and the number of confirmations required.
     pub fn initialize(&mut self, owners: Vec<Address>, num_confirmations_required: U256) -> Result<(), MultiSigError> {
         // The owners must not be initialized.
-        if self.owners.len() > 0 {
+        if !self.owners.is_empty() {
             return Err(MultiSigError::AlreadyInitialized(AlreadyInitialized{}));
         }
 
         // The owners must not be empty.
-        if owners.len() == 0 {
+        if owners.is_empty() {
             return Err(MultiSigError::ZeroOwners(ZeroOwners{}));
         }
 
@@ -160,133 +243,167 @@ impl MultiSig {
         Ok(())
     }
 
-    // The `execute_transaction` method executes a transaction.
-    pub fn execute_transaction(&mut self, tx_index: U256) -> Result<(), MultiSigError>{
-        // The sender must be an owner.
-        if !self.is_owner.get(msg::sender()) {
-            return Err(MultiSigError::NotOwner(NotOwner{}));
-        }
+    // The `execute_transaction` method executes a transaction, reverting with the callee's revert data if the call fails.
+    pub fn execute_transaction(&mut self, tx_index: U256) -> Result<(), MultiSigError> {
+        self.execute(tx_index, true)
+    }
 
-        // The transaction must exist.
-        let tx_index = tx_index.to::<usize>();
-        if tx_index >= self.transactions.len() {
-            return Err(MultiSigError::TxDoesNotExist(TxDoesNotExist{}));
-        }
+    // The `try_execute_transaction` method executes a transaction, but records a failed call
+    // with the `ExecutionFailure` event instead of reverting, so the transaction is consumed.
+    pub fn try_execute_transaction(&mut self, tx_index: U256) -> Result<(), MultiSigError> {
+        self.execute(tx_index, false)
+    }
 
-        // Try get transaction and check transaction is valid or not, if valid, execute it, if not, revert tx.
-        if let Some(mut entry) = self.transactions.get_mut(tx_index) {
-            if entry.executed.get() {
-                return Err(MultiSigError::TxAlreadyExecuted(TxAlreadyExecuted{}));
-            }
+    // The `execute_transactions` method executes several transactions, reverting if any of them cannot be executed.
+    pub fn execute_transactions(&mut self, tx_indices: Vec<U256>) -> Result<(), MultiSigError> {
+        for tx_index in tx_indices {
+            self.execute_transaction(tx_index)?;
+        }
+        Ok(())
+    }
 
-            if entry.num_confirmations.get() < self.num_confirmations_required.get() {
-                return Err(MultiSigError::ConfirmationNumberNotEnough(ConfirmationNumberNotEnough{}));
-            }
-            
-            entry.executed.set(true);
-            let entry_value = entry.value.get();
-            let entry_to = entry.to.get();
-            let entry_data = entry.data.get_bytes();
-            // Execute the transaction
-            match call(Call::new_in(self).value(entry_value), entry_to, &entry_data) {
-                // If the transaction is successful, emit the `ExecuteTransaction` event.
-                Ok(_) => {
-                    evm::log(ExecuteTransaction {
-                        owner: msg::sender(),
-                        txIndex: U256::from(tx_index),
-                    });
-                    Ok(())
-                },
-                // If the transaction fails, revert the transaction.
-                Err(_) => {
-                    return Err(MultiSigError::ExecuteFailed(ExecuteFailed{}));
-                }
-            }
-            
-        } else {
-            return Err(MultiSigError::TxDoesNotExist(TxDoesNotExist{}));
+    // The `confirm_transactions` method confirms several transactions, reverting if any of them cannot be confirmed.
+    pub fn confirm_transactions(&mut self, tx_indices: Vec<U256>) -> Result<(), MultiSigError> {
+        for tx_index in tx_indices {
+            self.confirm_transaction(tx_index)?;
         }
+        Ok(())
     }
 
     // The `confirm_transaction` method confirms a transaction.
     pub fn confirm_transaction(&mut self, tx_index: U256) -> Result<(), MultiSigError> {
-        // The sender must be an owner.
-        if !self.is_owner.get(msg::sender()) {
-            return Err(MultiSigError::NotOwner(NotOwner{}));
-        }
+        // The sender must be an owner and the transaction must still be pending.
+        self.check_pending(tx_index)?;
 
-        // The transaction must exist.
-        if tx_index >= U256::from(self.transactions.len()) {
-            return Err(MultiSigError::TxDoesNotExist(TxDoesNotExist{}));
+        if self.is_confirmed.get(tx_index).get(msg::sender()) {
+            return Err(MultiSigError::TxAlreadyConfirmed(TxAlreadyConfirmed{}));
         }
 
-        // Try get transaction and check transaction is valid or not, if valid, confirm it, if not, revert tx.
-        if let Some(mut entry) = self.transactions.get_mut(tx_index) {
-            if entry.executed.get() {
-                return Err(MultiSigError::TxAlreadyExecuted(TxAlreadyExecuted{}));
-            }
-
-            if self.is_confirmed.get(tx_index).get(msg::sender()) {
-                return Err(MultiSigError::TxAlreadyConfirmed(TxAlreadyConfirmed{}));
-            }
-
-            // Confirm the transaction
-            let num_confirmations = entry.num_confirmations.get();
-            entry.num_confirmations.set(num_confirmations + U256::from(1));
-            // Set the transaction as confirmed by the sender.
-            let mut tx_confirmed_info = self.is_confirmed.setter(tx_index);
-            let mut confirmed_by_address = tx_confirmed_info.setter(msg::sender());
-            confirmed_by_address.set(true);
+        // Confirm the transaction
+        let mut entry = self.transactions.setter(tx_index).unwrap();
+        let num_confirmations = entry.num_confirmations.get();
+        entry.num_confirmations.set(num_confirmations + U256::from(1));
+        // Set the transaction as confirmed by the sender.
+        self.is_confirmed.setter(tx_index).setter(msg::sender()).set(true);
 
-            // Emit the `ConfirmTransaction` event.
-            evm::log(ConfirmTransaction {
-                owner: msg::sender(),
-                txIndex: U256::from(tx_index),
-            });
-            Ok(())
-        } else {
-            return Err(MultiSigError::TxDoesNotExist(TxDoesNotExist{}));
-        }
+        // Emit the `ConfirmTransaction` event.
+        evm::log(ConfirmTransaction {
+            owner: msg::sender(),
+            txIndex: tx_index,
+        });
+        Ok(())
     }
 
     // The `revoke_confirmation` method revokes a confirmation for a transaction.
     pub fn revoke_confirmation(&mut self, tx_index: U256) -> Result<(), MultiSigError> {
         // The sender must be an owner.
-        if !self.is_owner.get(msg::sender()) {
-            return Err(MultiSigError::NotOwner(NotOwner{}));
+        self.check_owner()?;
+
+        // Check if the transaction has been confirmed or not, which also implies that it exists.
+        if !self.is_confirmed.get(tx_index).get(msg::sender()) {
+            // If the transaction has not been confirmed, return an error.
+            return Err(MultiSigError::TxNotConfirmed(TxNotConfirmed{}));
         }
-        // let tx_index = tx_index.to;
-        if tx_index >= U256::from(self.transactions.len()) {
-            return Err(MultiSigError::TxDoesNotExist(TxDoesNotExist{}));
+
+        //  Revoke the transaction
+        let mut entry = self.transactions.setter(tx_index).unwrap();
+        let num_confirmations = entry.num_confirmations.get();
+        entry.num_confirmations.set(num_confirmations - U256::from(1));
+        // Set the transaction as not confirmed by the sender.
+        self.is_confirmed.setter(tx_index).setter(msg::sender()).set(false);
+
+        //  Emit the `RevokeConfirmation` event.
+        evm::log(RevokeConfirmation {
+            owner: msg::sender(),
+            txIndex: tx_index,
+        });
+        Ok(())
+    }
+
+    // The `cancel_transaction` method votes to cancel a transaction.
+    // Once as many owners as required for execution have voted, the transaction is void.
+    pub fn cancel_transaction(&mut self, tx_index: U256) -> Result<(), MultiSigError> {
+        // The sender must be an owner and the transaction must still be pending.
+        self.check_pending(tx_index)?;
+
+        if self.is_cancel_confirmed.get(tx_index).get(msg::sender()) {
+            return Err(MultiSigError::CancellationAlreadyConfirmed(CancellationAlreadyConfirmed{}));
         }
 
-        // Try get transaction and check transaction is valid or not, if valid, revoke it, if not, revert tx.
-        if let Some(mut entry) = self.transactions.get_mut(tx_index) {
-            // Check if the transaction has been confirmed or not
-            if !self.is_confirmed.get(tx_index).get(msg::sender()) {
-                // If the transaction has not been confirmed, return an error.
-                return Err(MultiSigError::TxNotConfirmed(TxNotConfirmed{}));
-            }
+        // Record the cancellation vote of the sender.
+        let num_confirmations_required = self.num_confirmations_required.get();
+        let mut entry = self.transactions.setter(tx_index).unwrap();
+        let num_cancellations = entry.num_cancellations.get() + U256::from(1);
+        entry.num_cancellations.set(num_cancellations);
 
-            //  Revoke the transaction
-            let num_confirmations = entry.num_confirmations.get();
-            entry.num_confirmations.set(num_confirmations - U256::from(1));
-            // Set the transaction as not confirmed by the sender.
-            let mut tx_confirmed_info = self.is_confirmed.setter(tx_index);
-            let mut confirmed_by_address = tx_confirmed_info.setter(msg::sender());
-            confirmed_by_address.set(false);
+        // Mark the transaction as void once the quorum is reached.
+        let cancelled = num_cancellations >= num_confirmations_required;
+        if cancelled {
+            entry.cancelled.set(true);
+        }
+        self.is_cancel_confirmed.setter(tx_index).setter(msg::sender()).set(true);
 
-            //  Emit the `RevokeConfirmation` event.
-            evm::log(RevokeConfirmation {
+        // Emit the `ConfirmCancellation` event, and `CancelTransaction` once the transaction is void.
+        evm::log(ConfirmCancellation {
+            owner: msg::sender(),
+            txIndex: tx_index,
+        });
+        if cancelled {
+            evm::log(CancelTransaction {
                 owner: msg::sender(),
-                txIndex: U256::from(tx_index),
+                txIndex: tx_index,
             });
-            Ok(())
+        }
+        Ok(())
+    }
+
+    // The `sign_message` method marks a message hash as signed by the wallet, for ERC-1271 `isValidSignature`.
+    // It can only be called by the wallet itself, so it has to be submitted and confirmed as a transaction to this contract.
+    pub fn sign_message(&mut self, msg_hash: FixedBytes<32>) -> Result<(), MultiSigError> {
+        self.check_wallet()?;
+        self.signed_messages.setter(msg_hash).set(true);
+
+        // Emit the `SignMessage` event.
+        evm::log(SignMessage {
+            msgHash: msg_hash,
+        });
+        Ok(())
+    }
+
+    // The `is_valid_signature` method implements ERC-1271, returning the magic value `0x1626ba7e` for valid signatures.
+    // An empty signature is valid if the wallet signed the hash through `sign_message`. Otherwise the signature must be
+    // the concatenation of at least `num_confirmations_required` owner signatures over `get_message_hash(hash)`,
+    // ordered by ascending signer address.
+    pub fn is_valid_signature(&self, hash: FixedBytes<32>, signature: Bytes) -> FixedBytes<4> {
+        let valid = if signature.is_empty() {
+            self.signed_messages.get(hash)
         } else {
-            return Err(MultiSigError::TxDoesNotExist(TxDoesNotExist{}));
+            self.check_owner_signatures(hash, &signature)
+        };
+
+        if valid {
+            FixedBytes(ERC1271_MAGIC_VALUE)
+        } else {
+            FixedBytes(ERC1271_INVALID_VALUE)
         }
     }
 
+    // The `get_message_hash` method returns the EIP-712 digest that owners sign for `is_valid_signature`:
+    // a `MultiSigMessage(bytes32 hash)` in the domain `EIP712Domain(uint256 chainId,address verifyingContract)`
+    // of this wallet, so that a signature cannot be replayed on another wallet or chain with the same owners.
+    pub fn get_message_hash(&self, hash: FixedBytes<32>) -> FixedBytes<32> {
+        let domain_separator = keccak(
+            [
+                keccak(DOMAIN_TYPE).as_slice(),
+                &U256::from(block::chainid()).to_be_bytes::<32>(),
+                contract::address().into_word().as_slice(),
+            ]
+            .concat(),
+        );
+        let struct_hash = keccak([keccak(MULTISIG_MESSAGE_TYPE).as_slice(), hash.as_slice()].concat());
+        keccak([&[0x19, 0x01], domain_separator.as_slice(), struct_hash.as_slice()].concat())
+    }
+
     // The `is_owner` method checks if an address is an owner.
     pub fn is_owner(&self, check_address: Address) -> bool {
         self.is_owner.get(check_address)
@@ -296,4 +413,129 @@ impl MultiSig {
     pub fn get_transaction_count(&self) -> U256 {
         U256::from(self.transactions.len())
     }
-}
\ No newline at end of file
+}
+
+impl MultiSig {
+    // Returns an error if the sender is not an owner.
+    fn check_owner(&self) -> Result<(), MultiSigError> {
+        if !self.is_owner.get(msg::sender()) {
+            return Err(MultiSigError::NotOwner(NotOwner{}));
+        }
+        Ok(())
+    }
+
+    // Returns an error if the sender is not the wallet itself.
+    fn check_wallet(&self) -> Result<(), MultiSigError> {
+        if msg::sender() != contract::address() {
+            return Err(MultiSigError::NotWallet(NotWallet{}));
+        }
+        Ok(())
+    }
+
+    // Returns an error if the sender is not an owner, or if the transaction does not exist,
+    // has been executed or cancelled, or has expired.
+    fn check_pending(&self, tx_index: U256) -> Result<(), MultiSigError> {
+        self.check_owner()?;
+
+        let Some(entry) = self.transactions.get(tx_index) else {
+            return Err(MultiSigError::TxDoesNotExist(TxDoesNotExist{}));
+        };
+
+        if entry.executed.get() {
+            return Err(MultiSigError::TxAlreadyExecuted(TxAlreadyExecuted{}));
+        }
+
+        if entry.cancelled.get() {
+            return Err(MultiSigError::TransactionCancelled(TransactionCancelled{txIndex: tx_index}));
+        }
+
+        let expires_at = entry.expires_at.get();
+        if expires_at != U256::ZERO && U256::from(block::timestamp()) > expires_at {
+            return Err(MultiSigError::TransactionExpired(TransactionExpired{txIndex: tx_index, expiresAt: expires_at}));
+        }
+        Ok(())
+    }
+
+    // Checks that `signatures` holds enough distinct owner signatures over `get_message_hash(hash)`.
+    fn check_owner_signatures(&self, hash: FixedBytes<32>, signatures: &[u8]) -> bool {
+        if signatures.len() % SIGNATURE_LENGTH != 0 {
+            return false;
+        }
+
+        let num_signatures = U256::from(signatures.len() / SIGNATURE_LENGTH);
+        if num_signatures == U256::ZERO || num_signatures < self.num_confirmations_required.get() {
+            return false;
+        }
+
+        let message_hash = self.get_message_hash(hash);
+        let mut last_signer = Address::ZERO;
+        for signature in signatures.chunks(SIGNATURE_LENGTH) {
+            let Some(signer) = self.ecrecover_call(message_hash, signature) else {
+                return false;
+            };
+
+            // Signers must be sorted in ascending order, which also rules out counting an owner twice.
+            if signer <= last_signer || !self.is_owner.get(signer) {
+                return false;
+            }
+            last_signer = signer;
+        }
+        true
+    }
+
+    // Invokes the ECRECOVER precompile with `hash`, `v`, `r` and `s`, returning `None` if no signer can be recovered.
+    fn ecrecover_call(&self, hash: FixedBytes<32>, signature: &[u8]) -> Option<Address> {
+        let mut input = [0u8; 128];
+        input[..32].copy_from_slice(hash.as_slice());
+        input[63] = signature[64];
+        input[64..].copy_from_slice(&signature[..64]);
+        let result = static_call(self, ECRECOVER, &input).ok()?;
+        let signer = Address::from_slice(result.get(12..32)?);
+        (signer != Address::ZERO).then_some(signer)
+    }
+
+    // Shared by `execute_transaction` and `try_execute_transaction`.
+    fn execute(&mut self, tx_index: U256, revert_on_failure: bool) -> Result<(), MultiSigError> {
+        // The sender must be an owner and the transaction must still be pending.
+        self.check_pending(tx_index)?;
+
+        let num_confirmations_required = self.num_confirmations_required.get();
+        let mut entry = self.transactions.setter(tx_index).unwrap();
+        if entry.num_confirmations.get() < num_confirmations_required {
+            return Err(MultiSigError::ConfirmationNumberNotEnough(ConfirmationNumberNotEnough{}));
+        }
+
+        entry.executed.set(true);
+        let entry_value = entry.value.get();
+        let entry_to = entry.to.get();
+        let mut entry_data = Vec::with_capacity(entry.data.len() * 32);
+        for index in 0..entry.data.len() {
+            entry_data.extend_from_slice(entry.data.get(index).unwrap_or_default().as_slice());
+        }
+        entry_data.truncate(entry.data_length.get().to::<usize>());
+        // Execute the transaction
+        match call(Call::new_in(self).value(entry_value), entry_to, &entry_data) {
+            // If the transaction is successful, emit the `ExecuteTransaction` event.
+            Ok(_) => {
+                evm::log(ExecuteTransaction {
+                    owner: msg::sender(),
+                    txIndex: tx_index,
+                });
+                Ok(())
+            },
+            // If the transaction fails, either revert with the callee's revert data or log it.
+            Err(err) => {
+                let reason: Vec<u8> = err.into();
+                if revert_on_failure {
+                    return Err(MultiSigError::ExecuteFailed(ExecuteFailed{reason: reason.into()}));
+                }
+                evm::log(ExecutionFailure {
+                    owner: msg::sender(),
+                    txIndex: tx_index,
+                    reason: reason.into(),
+                });
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,416 @@
+// Allow `cargo stylus export-abi` to generate a main function.
+#![cfg_attr(not(feature = "export-abi"), no_main)]
+extern crate alloc;
+
+/// Import items from the SDK. The prelude contains common traits and macros.
+use stylus_sdk::{alloy_primitives::{Address, FixedBytes, U256}, block, call::{transfer_eth, Call}, contract, crypto::keccak, evm, msg, prelude::*};
+// Because the naming of alloy_primitives and alloy_sol_types is the same, so we need to re-name the types in alloy_sol_types
+use alloy_sol_types::{sol, sol_data::{Address as SOLAddress, FixedBytes as SOLFixedBytes, Uint}, SolType};
+
+// Import the IERC721 interface.
+sol_interface! {
+    interface IERC721 {
+        // Required methods.
+        function safeTransferFrom(address from, address to, uint256 token_id) external;
+        function transferFrom(address, address, uint256) external;
+    }
+}
+
+// Define the events and errors for the contract.
+sol!{
+    // Define the events for the contract.
+    event Start(); // Start the auction.
+    event Commit(address indexed bidder, bytes32 commitment, uint256 deposit); // Commit to a sealed bid.
+    event Reveal(address indexed bidder, uint256 amount, bool valid); // Open a sealed bid.
+    event Withdraw(address indexed bidder, uint256 amount); // Withdraw a deposit.
+    event End(address winner, uint256 price); // End the auction.
+
+    // Define the errors for the contract.
+    error AlreadyInitialized(); // The contract has already been initialized.
+    error AlreadyStarted(); // The auction has already started.
+    error NotSeller(); // The sender is not the seller.
+    error InvalidDuration(); // The duration of a phase is zero.
+    error NotCommitPhase(); // Bids can only be committed before the commit phase ends.
+    error NotRevealPhase(); // Bids can only be revealed between the end of the commit phase and the end of the reveal phase.
+    error AlreadyRevealed(); // The bid of the sender has already been revealed.
+    error InvalidReveal(); // The bid and salt do not match the commitment.
+    error NotEnded(); // The reveal phase has not ended.
+    error AuctionEnded(); // The auction has already ended.
+    error NotSettled(); // The auction has not been ended yet.
+    error NftTransferFailed(bytes reason); // A transfer of the NFT failed.
+    error TransferFailed(bytes reason); // A transfer of ETH failed.
+}
+
+#[derive(SolidityError)]
+pub enum SealedBidAuctionError {
+    // Define the errors for the contract.
+    AlreadyInitialized(AlreadyInitialized),
+    AlreadyStarted(AlreadyStarted),
+    NotSeller(NotSeller),
+    InvalidDuration(InvalidDuration),
+    NotCommitPhase(NotCommitPhase),
+    NotRevealPhase(NotRevealPhase),
+    AlreadyRevealed(AlreadyRevealed),
+    InvalidReveal(InvalidReveal),
+    NotEnded(NotEnded),
+    AuctionEnded(AuctionEnded),
+    NotSettled(NotSettled),
+    NftTransferFailed(NftTransferFailed),
+    TransferFailed(TransferFailed),
+}
+
+// Define some persistent storage using the Solidity ABI.
+// `SealedBidAuction` will be the entrypoint.
+sol_storage! {
+    #[entrypoint]
+    pub struct SealedBidAuction {
+        address nft_address; // The address of the NFT contract.
+        uint256 nft_id; // The ID of the NFT.
+
+        address seller; // The address of the seller.
+        uint256 reserve_price; // Bids below this price are not valid, and the winner pays at least this price.
+        uint256 commit_duration; // The duration of the commit phase once started.
+        uint256 reveal_duration; // The duration of the reveal phase after the commit phase.
+        bool slash_unrevealed; // Unrevealed deposits go to the seller if set, or are refunded otherwise.
+        uint256 commit_end; // The end time of the commit phase.
+        uint256 reveal_end; // The end time of the reveal phase.
+        bool started; // The auction has started or not.
+        bool ended; // The auction has ended or not.
+
+        mapping(address => bytes32) commitments; // The sealed bids of the bidders.
+        mapping(address => uint256) deposits; // The deposits of the bidders.
+        mapping(address => bool) revealed; // The bidders who revealed their bid.
+        uint256 unrevealed_deposits; // The total deposits of the bidders who did not reveal their bid.
+
+        address highest_bidder; // The address of the highest bidder.
+        uint256 highest_bid; // The highest revealed bid.
+        uint256 second_highest_bid; // The second highest revealed bid, the price the winner pays.
+        uint256 proceeds; // The ETH owed to the seller once the auction has ended, for the seller to withdraw.
+    }
+}
+
+/// Declare that `SealedBidAuction` is a contract with the following external methods.
+#[public]
+impl SealedBidAuction {
+    // Get nft address
+    pub fn nft(&self) -> Result<Address, SealedBidAuctionError> {
+        Ok(self.nft_address.get())
+    }
+
+    // Get nft id
+    pub fn nft_id(&self) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.nft_id.get())
+    }
+
+    // Get seller address
+    pub fn seller(&self) -> Result<Address, SealedBidAuctionError> {
+        Ok(self.seller.get())
+    }
+
+    // Get the reserve price
+    pub fn reserve_price(&self) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.reserve_price.get())
+    }
+
+    // Get whether unrevealed deposits go to the seller
+    pub fn slash_unrevealed(&self) -> Result<bool, SealedBidAuctionError> {
+        Ok(self.slash_unrevealed.get())
+    }
+
+    // Get the end time of the commit phase
+    pub fn commit_end(&self) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.commit_end.get())
+    }
+
+    // Get the end time of the reveal phase
+    pub fn reveal_end(&self) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.reveal_end.get())
+    }
+
+    // Get started status
+    pub fn started(&self) -> Result<bool, SealedBidAuctionError> {
+        Ok(self.started.get())
+    }
+
+    // Get ended status
+    pub fn ended(&self) -> Result<bool, SealedBidAuctionError> {
+        Ok(self.ended.get())
+    }
+
+    // Get the commitment of a bidder
+    pub fn commitments(&self, bidder: Address) -> Result<FixedBytes<32>, SealedBidAuctionError> {
+        Ok(self.commitments.get(bidder))
+    }
+
+    // Get the deposit of a bidder
+    pub fn deposits(&self, bidder: Address) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.deposits.get(bidder))
+    }
+
+    // Get whether a bidder revealed their bid
+    pub fn revealed(&self, bidder: Address) -> Result<bool, SealedBidAuctionError> {
+        Ok(self.revealed.get(bidder))
+    }
+
+    // Get highest bidder address
+    pub fn highest_bidder(&self) -> Result<Address, SealedBidAuctionError> {
+        Ok(self.highest_bidder.get())
+    }
+
+    // Get highest bid amount
+    pub fn highest_bid(&self) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.highest_bid.get())
+    }
+
+    // Get the price the highest bidder pays: the second highest bid, but at least the reserve price
+    pub fn price(&self) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.second_highest_bid.get().max(self.reserve_price.get()))
+    }
+
+    // Get the proceeds the seller can withdraw
+    pub fn proceeds(&self) -> Result<U256, SealedBidAuctionError> {
+        Ok(self.proceeds.get())
+    }
+
+    // Get the commitment for a bid of `bidder`, keccak256(abi.encode(auction, bidder, amount, salt))
+    // The auction and the bidder are part of the commitment, so that no one can copy the commitment of another
+    // bidder and replay its reveal, and a commitment cannot be reused in another auction.
+    pub fn get_commitment(&self, bidder: Address, amount: U256, salt: FixedBytes<32>) -> FixedBytes<32> {
+        // define sol types tuple
+        type CommitmentHashType = (SOLAddress, SOLAddress, Uint<256>, SOLFixedBytes<32>);
+        // set the tuple
+        let commitment_data = (contract::address(), bidder, amount, salt);
+        // encode the tuple
+        let commitment_data_encode = CommitmentHashType::abi_encode_sequence(&commitment_data);
+        // hash the encoded data
+        keccak(commitment_data_encode)
+    }
+
+    // Initialize program
+    pub fn initialize(
+        &mut self,
+        nft: Address,
+        nft_id: U256,
+        reserve_price: U256,
+        commit_duration: U256,
+        reveal_duration: U256,
+        slash_unrevealed: bool,
+    ) -> Result<(), SealedBidAuctionError> {
+        // Check if the contract has already been initialized.
+        if self.seller.get() != Address::default() {
+            // Return an error if the contract has already been initialized.
+            return Err(SealedBidAuctionError::AlreadyInitialized(AlreadyInitialized{}));
+        }
+
+        // Check that both phases will run for some time.
+        if commit_duration == U256::ZERO || reveal_duration == U256::ZERO {
+            return Err(SealedBidAuctionError::InvalidDuration(InvalidDuration{}));
+        }
+
+        // Initialize the contract with the NFT address, the NFT ID, the seller, and the auction rules.
+        self.nft_address.set(nft);
+        self.nft_id.set(nft_id);
+        self.seller.set(msg::sender());
+        self.reserve_price.set(reserve_price);
+        self.commit_duration.set(commit_duration);
+        self.reveal_duration.set(reveal_duration);
+        self.slash_unrevealed.set(slash_unrevealed);
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<(), SealedBidAuctionError> {
+        // Check if the auction has already started.
+        if self.started.get() {
+            return Err(SealedBidAuctionError::AlreadyStarted(AlreadyStarted{}));
+        }
+
+        // Check if the sender is the seller.
+        if self.seller.get() != msg::sender() {
+            // Return an error if the sender is not the seller.
+            return Err(SealedBidAuctionError::NotSeller(NotSeller{}));
+        }
+
+        // Transfer the NFT to the contract.
+        let nft = IERC721::new(*self.nft_address);
+        let nft_id = self.nft_id.get();
+        let config = Call::new_in(self);
+        if let Err(err) = nft.transfer_from(config, msg::sender(), contract::address(), nft_id) {
+            let reason: Vec<u8> = err.into();
+            return Err(SealedBidAuctionError::NftTransferFailed(NftTransferFailed{reason: reason.into()}));
+        }
+
+        // The commit phase starts now and is followed by the reveal phase.
+        self.started.set(true);
+        let commit_end = U256::from(block::timestamp()) + self.commit_duration.get();
+        self.commit_end.set(commit_end);
+        self.reveal_end.set(commit_end + self.reveal_duration.get());
+        // Log the start event.
+        evm::log(Start {});
+        Ok(())
+    }
+
+    // The commit method lets bidders submit a sealed bid, `getCommitment(bidder, amount, salt)`, along with a deposit.
+    // The deposit should cover the bid and can be larger, so that it does not give away the bid.
+    // Committing again replaces the commitment and adds to the deposit.
+    #[payable]
+    pub fn commit(&mut self, commitment: FixedBytes<32>) -> Result<(), SealedBidAuctionError> {
+        // Check if the auction is in the commit phase.
+        if !self.started.get() || U256::from(block::timestamp()) >= self.commit_end.get() {
+            return Err(SealedBidAuctionError::NotCommitPhase(NotCommitPhase{}));
+        }
+
+        // Store the commitment and add to the deposit.
+        self.commitments.insert(msg::sender(), commitment);
+        let deposit = self.deposits.get(msg::sender()) + msg::value();
+        self.deposits.insert(msg::sender(), deposit);
+        let unrevealed_deposits = self.unrevealed_deposits.get();
+        self.unrevealed_deposits.set(unrevealed_deposits + msg::value());
+
+        // Log the commit event.
+        evm::log(Commit {
+            bidder: msg::sender(),
+            commitment,
+            deposit,
+        });
+        Ok(())
+    }
+
+    // The reveal method lets bidders open their sealed bid.
+    // A bid above the deposit or below the reserve price is not valid, and its deposit is refunded.
+    pub fn reveal(&mut self, amount: U256, salt: FixedBytes<32>) -> Result<(), SealedBidAuctionError> {
+        // Check if the auction is in the reveal phase.
+        let now = U256::from(block::timestamp());
+        if !self.started.get() || now < self.commit_end.get() || now >= self.reveal_end.get() {
+            return Err(SealedBidAuctionError::NotRevealPhase(NotRevealPhase{}));
+        }
+
+        // Check if the bid has already been revealed.
+        let bidder = msg::sender();
+        if self.revealed.get(bidder) {
+            return Err(SealedBidAuctionError::AlreadyRevealed(AlreadyRevealed{}));
+        }
+
+        // Check if the bid matches the commitment.
+        if self.get_commitment(bidder, amount, salt) != self.commitments.get(bidder) {
+            return Err(SealedBidAuctionError::InvalidReveal(InvalidReveal{}));
+        }
+
+        // The deposit is no longer at stake.
+        self.revealed.insert(bidder, true);
+        let deposit = self.deposits.get(bidder);
+        let unrevealed_deposits = self.unrevealed_deposits.get();
+        self.unrevealed_deposits.set(unrevealed_deposits - deposit);
+
+        // Update the highest and second highest bids, the first bid revealed wins a tie.
+        let valid = amount <= deposit && amount >= self.reserve_price.get();
+        if valid {
+            if amount > self.highest_bid.get() {
+                self.second_highest_bid.set(self.highest_bid.get());
+                self.highest_bid.set(amount);
+                self.highest_bidder.set(bidder);
+            } else if amount > self.second_highest_bid.get() {
+                self.second_highest_bid.set(amount);
+            }
+        }
+
+        // Log the reveal event.
+        evm::log(Reveal {
+            bidder,
+            amount,
+            valid,
+        });
+        Ok(())
+    }
+
+    // The withdraw method lets bidders withdraw their deposit once the auction has ended.
+    // The winner gets back their deposit minus the price, and the seller withdraws the proceeds.
+    pub fn withdraw(&mut self) -> Result<(), SealedBidAuctionError> {
+        // Check if the auction has ended.
+        if !self.ended.get() {
+            return Err(SealedBidAuctionError::NotSettled(NotSettled{}));
+        }
+
+        // Unrevealed deposits have been credited to the seller if they are slashed.
+        let bidder = msg::sender();
+        let mut amount = U256::ZERO;
+        if !self.slash_unrevealed.get() || self.revealed.get(bidder) {
+            // Set the deposit of this bidder to 0.
+            amount = self.deposits.get(bidder);
+            self.deposits.insert(bidder, U256::ZERO);
+        }
+
+        // Set the proceeds to 0 if the sender is the seller.
+        if bidder == self.seller.get() {
+            amount += self.proceeds.get();
+            self.proceeds.set(U256::ZERO);
+        }
+
+        // Transfer the amount back.
+        pay(bidder, amount)?;
+
+        // Log the withdraw event.
+        evm::log(Withdraw {
+            bidder,
+            amount,
+        });
+        Ok(())
+    }
+
+    // The end method lets anyone end the auction after the reveal phase.
+    // The highest bidder receives the NFT and pays the second highest bid, everyone else can withdraw their deposit.
+    pub fn end(&mut self) -> Result<(), SealedBidAuctionError> {
+        // Check if the reveal phase has ended.
+        if !self.started.get() || U256::from(block::timestamp()) < self.reveal_end.get() {
+            return Err(SealedBidAuctionError::NotEnded(NotEnded{}));
+        }
+
+        // Check if the auction has already ended.
+        if self.ended.get() {
+            return Err(SealedBidAuctionError::AuctionEnded(AuctionEnded{}));
+        }
+        self.ended.set(true);
+
+        // Charge the winner the price out of their deposit.
+        let seller = self.seller.get();
+        let winner = self.highest_bidder.get();
+        let sold = winner != Address::default();
+        let price = if sold { self.price()? } else { U256::ZERO };
+        if sold {
+            let deposit = self.deposits.get(winner);
+            self.deposits.insert(winner, deposit - price);
+        }
+
+        // Transfer the NFT to the winner, or back to the seller if there was no valid bid.
+        let nft = IERC721::new(*self.nft_address);
+        let nft_id = self.nft_id.get();
+        let config = Call::new_in(self);
+        let to = if sold { winner } else { seller };
+        if let Err(err) = nft.transfer_from(config, contract::address(), to, nft_id) {
+            let reason: Vec<u8> = err.into();
+            return Err(SealedBidAuctionError::NftTransferFailed(NftTransferFailed{reason: reason.into()}));
+        }
+
+        // Credit the seller with the price, and the unrevealed deposits if they are slashed.
+        // The seller withdraws them, so that a seller that rejects ETH cannot block the end of the auction.
+        let mut proceeds = price;
+        if self.slash_unrevealed.get() {
+            proceeds += self.unrevealed_deposits.get();
+        }
+        self.proceeds.set(proceeds);
+
+        // Log the end event.
+        evm::log(End {
+            winner,
+            price,
+        });
+        Ok(())
+    }
+}
+
+// Sends `amount` of ETH to `to`, skipping empty transfers.
+fn pay(to: Address, amount: U256) -> Result<(), SealedBidAuctionError> {
+    if amount == U256::ZERO {
+        return Ok(());
+    }
+    transfer_eth(to, amount).map_err(|reason| SealedBidAuctionError::TransferFailed(TransferFailed{reason: reason.into()}))
+}
@@ -0,0 +1,141 @@
+// Allow `cargo stylus export-abi` to generate a main function.
+#![cfg_attr(not(feature = "export-abi"), no_main)]
+extern crate alloc;
+
+// Modules and imports
+mod spending_limit;
+
+/// Import items from the SDK. The prelude contains common traits and macros.
+use stylus_sdk::{contract, evm, msg, prelude::*, call::{Call, transfer_eth}, alloy_primitives::{Address, U256}};
+use alloy_sol_types::sol;
+use crate::spending_limit::{SpendingLimits, SpendingLimitExceeded, SpendWithinLimit};
+
+// Import the interfaces of the MultiSig wallet, used to check its owners,
+// and of ERC-20 tokens, used to pay out tokens within the spending limit.
+sol_interface! {
+    interface IMultiSig {
+        function isOwner(address check_address) external view returns (bool);
+    }
+
+    interface IERC20 {
+        function transfer(address to, uint256 value) external returns (bool);
+    }
+}
+
+// Define some events using the Solidity ABI.
+sol! {
+    event Deposit(address indexed sender, uint256 amount, uint256 balance);
+
+    // Error types for the SpendingLimitModule contract
+    error AlreadyInitialized();
+    error InvalidWallet(); // The wallet address is the zero address when init.
+    error NotOwner(); // The sender is not an owner of the wallet.
+    error NotWallet(); // The sender is not the wallet, i.e. the call did not go through a confirmed transaction.
+    error TransferFailed(bytes reason);
+}
+
+// Define some persistent storage using the Solidity ABI.
+// `SpendingLimitModule` will be the entrypoint.
+sol_storage! {
+    #[entrypoint]
+    pub struct SpendingLimitModule {
+        address wallet; // The MultiSig wallet whose owners can spend from this module
+        SpendingLimits spending_limits; // The daily allowances owners can spend without confirmations
+    }
+}
+
+// Error types for the SpendingLimitModule contract
+#[derive(SolidityError)]
+pub enum SpendingLimitError {
+    AlreadyInitialized(AlreadyInitialized),
+    InvalidWallet(InvalidWallet),
+    NotOwner(NotOwner),
+    NotWallet(NotWallet),
+    TransferFailed(TransferFailed),
+    SpendingLimitExceeded(SpendingLimitExceeded),
+}
+
+/// Declare that `SpendingLimitModule` is a contract with the following external methods.
+#[public]
+impl SpendingLimitModule {
+    // The `initialize` method attaches the module to a MultiSig wallet, it can only be called once.
+    pub fn initialize(&mut self, wallet: Address) -> Result<(), SpendingLimitError> {
+        if self.wallet.get() != Address::ZERO {
+            return Err(SpendingLimitError::AlreadyInitialized(AlreadyInitialized{}));
+        }
+        if wallet == Address::ZERO {
+            return Err(SpendingLimitError::InvalidWallet(InvalidWallet{}));
+        }
+        self.wallet.set(wallet);
+        Ok(())
+    }
+
+    // The `wallet` method returns the MultiSig wallet the module is attached to.
+    pub fn wallet(&self) -> Address {
+        self.wallet.get()
+    }
+
+    // The `deposit` method is payable, so the module can be topped up with ETH.
+    // ERC-20 tokens are topped up by transferring them to the module.
+    #[payable]
+    pub fn deposit(&mut self) {
+        evm::log(Deposit {
+            sender: msg::sender(),
+            amount: msg::value(),
+            balance: contract::balance(),
+        });
+    }
+
+    // The `set_spending_limit` method sets the daily allowance of an asset, the zero address being ETH.
+    // It can only be called by the wallet, so it has to be submitted and confirmed as a MultiSig transaction to this module.
+    pub fn set_spending_limit(&mut self, asset: Address, amount: U256) -> Result<(), SpendingLimitError> {
+        if msg::sender() != self.wallet.get() {
+            return Err(SpendingLimitError::NotWallet(NotWallet{}));
+        }
+        self.spending_limits.set_limit(asset, amount);
+        Ok(())
+    }
+
+    // The `get_spending_limit` method returns the daily allowance of an asset,
+    // the amount spent in the current window and the timestamp at which the window resets.
+    pub fn get_spending_limit(&self, asset: Address) -> (U256, U256, U256) {
+        self.spending_limits.limit_of(asset)
+    }
+
+    // The `spend_within_limit` method lets a single owner of the wallet send ETH or ERC-20 tokens
+    // held by the module without confirmations, as long as the daily allowance of the asset is not exceeded.
+    pub fn spend_within_limit(&mut self, asset: Address, to: Address, amount: U256) -> Result<(), SpendingLimitError> {
+        // The sender must be an owner of the wallet.
+        let wallet = IMultiSig::new(self.wallet.get());
+        if !wallet.is_owner(&*self, msg::sender()).unwrap_or(false) {
+            return Err(SpendingLimitError::NotOwner(NotOwner{}));
+        }
+
+        // Record the spend before transferring, so the allowance cannot be exceeded by reentering.
+        self.spending_limits.spend(asset, amount)?;
+
+        if asset == Address::ZERO {
+            transfer_eth(self, to, amount)
+                .map_err(|reason| SpendingLimitError::TransferFailed(TransferFailed{reason: reason.into()}))?;
+        } else {
+            let token = IERC20::new(asset);
+            match token.transfer(Call::new_in(self), to, amount) {
+                Ok(true) => {},
+                Ok(false) => return Err(SpendingLimitError::TransferFailed(TransferFailed{reason: Vec::new().into()})),
+                Err(err) => {
+                    let reason: Vec<u8> = err.into();
+                    return Err(SpendingLimitError::TransferFailed(TransferFailed{reason: reason.into()}));
+                }
+            }
+        }
+
+        // Emit the `SpendWithinLimit` event.
+        evm::log(SpendWithinLimit {
+            owner: msg::sender(),
+            asset,
+            to,
+            amount,
+        });
+        Ok(())
+    }
+}
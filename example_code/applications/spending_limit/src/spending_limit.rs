@@ -0,0 +1,91 @@
+//! Daily spending limits for the SpendingLimit module
+//!
+//! The [`SpendingLimits`] type keeps a per-asset allowance that a single owner
+//! can spend without collecting confirmations. The zero address stands for ETH,
+//! any other asset is the address of an ERC-20 token.
+//!
+//! A window opens with the first spend after the previous window has elapsed
+//! and lasts [`SpendingLimits::WINDOW`] seconds, after which the full allowance
+//! is available again.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+// Imported packages
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::sol;
+use stylus_sdk::{block, evm, prelude::*};
+
+sol_storage! {
+    /// SpendingLimits tracks the allowance of every asset.
+    pub struct SpendingLimits {
+        /// Maps assets to their allowance
+        mapping(address => SpendingLimit) limits;
+    }
+
+    /// The allowance of a single asset.
+    pub struct SpendingLimit {
+        /// The amount that can be spent per window
+        uint256 amount;
+        /// The amount spent in the current window
+        uint256 spent;
+        /// The timestamp the current window started at
+        uint256 window_start;
+    }
+}
+
+// Declare events and Solidity error types
+sol! {
+    event SetSpendingLimit(address indexed asset, uint256 amount);
+    event SpendWithinLimit(address indexed owner, address indexed asset, address indexed to, uint256 amount);
+
+    error SpendingLimitExceeded(address asset, uint256 available, uint256 amount);
+}
+
+// These methods aren't exposed to other contracts
+// The SpendingLimitModule contract checks who is allowed to call them
+impl SpendingLimits {
+    /// Length of a spending window in seconds (24 hours)
+    pub const WINDOW: u64 = 86400;
+
+    /// Sets the allowance per window of an asset, keeping what was already spent in the current window
+    pub fn set_limit(&mut self, asset: Address, amount: U256) {
+        self.limits.setter(asset).amount.set(amount);
+        evm::log(SetSpendingLimit { asset, amount });
+    }
+
+    /// Returns the allowance of an asset, the amount spent in the current window
+    /// and the timestamp at which the window resets
+    pub fn limit_of(&self, asset: Address) -> (U256, U256, U256) {
+        let limit = self.limits.get(asset);
+        let resets_at = limit.window_start.get() + U256::from(Self::WINDOW);
+        if U256::from(block::timestamp()) >= resets_at {
+            // The window has elapsed, nothing has been spent in the next one yet
+            return (limit.amount.get(), U256::ZERO, U256::ZERO);
+        }
+        (limit.amount.get(), limit.spent.get(), resets_at)
+    }
+
+    /// Records a spend of `amount`, failing if it exceeds what is left of the allowance
+    pub fn spend(&mut self, asset: Address, amount: U256) -> Result<(), SpendingLimitExceeded> {
+        let now = U256::from(block::timestamp());
+        let mut limit = self.limits.setter(asset);
+
+        // Open a new window if the previous one has elapsed
+        if now >= limit.window_start.get() + U256::from(Self::WINDOW) {
+            limit.window_start.set(now);
+            limit.spent.set(U256::ZERO);
+        }
+
+        let spent = limit.spent.get();
+        let available = limit.amount.get().saturating_sub(spent);
+        if amount > available {
+            return Err(SpendingLimitExceeded {
+                asset,
+                available,
+                amount,
+            });
+        }
+        limit.spent.set(spent + amount);
+        Ok(())
+    }
+}
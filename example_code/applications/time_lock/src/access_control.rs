@@ -0,0 +1,149 @@
+//! Implementation of role-based access control
+//!
+//! The eponymous [`AccessControl`] type follows OpenZeppelin's `AccessControl`:
+//! every role is a `bytes32` identifier with a set of members and an admin role,
+//! whose members can grant and revoke it. Roles without an explicit admin role
+//! are administered by [`DEFAULT_ADMIN_ROLE`].
+//!
+//! [`AccessControl`] is intended to be inherited by other contract types.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+// Imported packages
+use alloy_primitives::{Address, FixedBytes};
+use alloy_sol_types::sol;
+use stylus_sdk::prelude::*;
+use crate::log;
+
+/// The admin role of every role that has not been given another one
+pub const DEFAULT_ADMIN_ROLE: FixedBytes<32> = FixedBytes::ZERO;
+
+sol_storage! {
+    /// AccessControl keeps the members and admin role of every role.
+    pub struct AccessControl {
+        /// Maps roles to their members and admin role
+        mapping(bytes32 => RoleData) roles;
+    }
+
+    /// The members and admin role of a single role.
+    pub struct RoleData {
+        /// Maps accounts to whether they have the role
+        mapping(address => bool) members;
+        /// The role whose members can grant and revoke this role
+        bytes32 admin_role;
+    }
+}
+
+// Declare events and Solidity error types
+sol! {
+    event RoleGranted(bytes32 indexed role, address indexed account, address indexed sender);
+    event RoleRevoked(bytes32 indexed role, address indexed account, address indexed sender);
+    event RoleAdminChanged(bytes32 indexed role, bytes32 indexed previousAdminRole, bytes32 indexed newAdminRole);
+
+    error AccessControlUnauthorizedAccount(address account, bytes32 neededRole);
+    error AccessControlBadConfirmation();
+}
+
+/// Represents the ways methods may fail.
+#[derive(SolidityError)]
+pub enum AccessControlError {
+    AccessControlUnauthorizedAccount(AccessControlUnauthorizedAccount),
+    AccessControlBadConfirmation(AccessControlBadConfirmation),
+}
+
+// These methods aren't exposed to other contracts
+// Methods marked as "pub" here are usable outside of the access_control module (i.e. they're callable from lib.rs)
+impl AccessControl {
+    /// Fails unless `account` has `role`
+    pub fn _check_role(&self, role: FixedBytes<32>, account: Address) -> Result<(), AccessControlUnauthorizedAccount> {
+        if !self.has_role(role, account) {
+            return Err(AccessControlUnauthorizedAccount {
+                account,
+                neededRole: role,
+            });
+        }
+        Ok(())
+    }
+
+    /// Grants `role` to `account` without checking the sender, returning whether it was granted
+    pub fn _grant_role(&mut self, role: FixedBytes<32>, account: Address) -> bool {
+        if self.has_role(role, account) {
+            return false;
+        }
+        self.roles.setter(role).members.insert(account, true);
+        log(self.vm(), RoleGranted {
+            role,
+            account,
+            sender: self.vm().msg_sender(),
+        });
+        true
+    }
+
+    /// Revokes `role` from `account` without checking the sender, returning whether it was revoked
+    pub fn _revoke_role(&mut self, role: FixedBytes<32>, account: Address) -> bool {
+        if !self.has_role(role, account) {
+            return false;
+        }
+        self.roles.setter(role).members.insert(account, false);
+        log(self.vm(), RoleRevoked {
+            role,
+            account,
+            sender: self.vm().msg_sender(),
+        });
+        true
+    }
+
+    /// Sets the role whose members can grant and revoke `role`
+    pub fn _set_role_admin(&mut self, role: FixedBytes<32>, admin_role: FixedBytes<32>) {
+        let previous_admin_role = self.get_role_admin(role);
+        self.roles.setter(role).admin_role.set(admin_role);
+        log(self.vm(), RoleAdminChanged {
+            role,
+            previousAdminRole: previous_admin_role,
+            newAdminRole: admin_role,
+        });
+    }
+}
+
+// These methods are public to other contracts
+#[public]
+impl AccessControl {
+    /// The admin role of every role that has not been given another one
+    #[selector(name = "DEFAULT_ADMIN_ROLE")]
+    pub fn default_admin_role(&self) -> FixedBytes<32> {
+        DEFAULT_ADMIN_ROLE
+    }
+
+    /// Whether `account` has `role`
+    pub fn has_role(&self, role: FixedBytes<32>, account: Address) -> bool {
+        self.roles.getter(role).members.get(account)
+    }
+
+    /// The role whose members can grant and revoke `role`
+    pub fn get_role_admin(&self, role: FixedBytes<32>) -> FixedBytes<32> {
+        self.roles.getter(role).admin_role.get()
+    }
+
+    /// Grants `role` to `account`, the sender must have the admin role of `role`
+    pub fn grant_role(&mut self, role: FixedBytes<32>, account: Address) -> Result<(), AccessControlError> {
+        self._check_role(self.get_role_admin(role), self.vm().msg_sender())?;
+        self._grant_role(role, account);
+        Ok(())
+    }
+
+    /// Revokes `role` from `account`, the sender must have the admin role of `role`
+    pub fn revoke_role(&mut self, role: FixedBytes<32>, account: Address) -> Result<(), AccessControlError> {
+        self._check_role(self.get_role_admin(role), self.vm().msg_sender())?;
+        self._revoke_role(role, account);
+        Ok(())
+    }
+
+    /// Revokes `role` from the sender, who has to confirm its own address
+    pub fn renounce_role(&mut self, role: FixedBytes<32>, caller_confirmation: Address) -> Result<(), AccessControlError> {
+        if caller_confirmation != self.vm().msg_sender() {
+            return Err(AccessControlError::AccessControlBadConfirmation(AccessControlBadConfirmation {}));
+        }
+        self._revoke_role(role, caller_confirmation);
+        Ok(())
+    }
+}
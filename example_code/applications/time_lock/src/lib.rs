@@ -1,44 +1,98 @@
 // Allow `cargo stylus export-abi` to generate a main function if the "export-abi" feature is enabled.
-#![cfg_attr(not(feature = "export-abi"), no_main)]
+#![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 extern crate alloc;
 
-use sha3::{Digest, Keccak256};
-use alloc::string::String;
-use alloy_primitives::{Address, FixedBytes, U256};
-use alloy_sol_types::{sol, sol_data::{Address as SOLAddress, Bytes as SOLBytes, *}, SolType};
+// Modules and imports
+mod access_control;
+
+use alloy_primitives::{b256, Address, FixedBytes, U256};
+use alloy_sol_types::{abi::token::WordToken, sol, sol_data::{Address as SOLAddress, Bytes as SOLBytes, FixedBytes as SOLFixedBytes, *}, SolEvent, SolType, TopicList};
 // Import items from the SDK. The prelude contains common traits and macros.
-use stylus_sdk::{abi::Bytes, block, call::{call, Call}, evm, msg, prelude::*};
+use stylus_sdk::{abi::Bytes, crypto::keccak, prelude::*, stylus_core::{calls::{CallContext, MutatingCallContext}, Host}};
+use crate::access_control::{AccessControl, AccessControlUnauthorizedAccount, DEFAULT_ADMIN_ROLE};
+
+// Define the types hashed into operation IDs, as `abi.encode` does in OpenZeppelin's `hashOperation` and `hashOperationBatch`.
+type OperationHashType = (SOLAddress, Uint<256>, SOLBytes, SOLFixedBytes<32>, SOLFixedBytes<32>);
+type BatchOperationHashType = (Array<SOLAddress>, Array<Uint<256>>, Array<SOLBytes>, SOLFixedBytes<32>, SOLFixedBytes<32>);
+
+// keccak256("PROPOSER_ROLE"), held by the accounts that can schedule operations.
+const PROPOSER_ROLE: FixedBytes<32> = b256!("b09aa5aeb3702cfd50b6b62bc4532604938f21248a27a1d5ca736082b6819cc1");
+// keccak256("EXECUTOR_ROLE"), held by the accounts that can execute operations. Granting it to the zero address lets anyone execute.
+const EXECUTOR_ROLE: FixedBytes<32> = b256!("d8aa0f3194971a2a116679f7c2090f6939c8d4e01a2a8d7e41d55e5351469e63");
+// keccak256("CANCELLER_ROLE"), held by the accounts that can cancel operations.
+const CANCELLER_ROLE: FixedBytes<32> = b256!("fd643c72710c63c0180259aba6b2d05451e3591a24e58b62239378085726f783");
+
+// Timestamp stored for executed operations, no operation can be scheduled for it.
+const DONE_TIMESTAMP: U256 = U256::from_limbs([1, 0, 0, 0]);
+
+// Emits an event, like `stylus_core::log` but without unwrapping the encoding of its topics,
+// which cannot fail for `sol!` events. The unwrap would link alloy's error formatting into the contract,
+// about 6 KB more than the 24 KB a Stylus contract can be once compressed.
+pub(crate) fn log<T: SolEvent>(vm: &dyn Host, event: T) {
+    let mut topics = [WordToken::default(); 4];
+    if event.encode_topics_raw(&mut topics).is_err() {
+        return;
+    }
+    let count = T::TopicList::COUNT;
+    let mut bytes = Vec::with_capacity(32 * count);
+    for topic in &topics[..count] {
+        bytes.extend_from_slice(topic.as_slice());
+    }
+    event.encode_data_to(&mut bytes);
+    vm.emit_log(&bytes, count);
+}
+
+// The state of an operation, as returned by `get_operation_state`.
+// The first four states are OpenZeppelin's, `Expired` is specific to this timelock's grace period.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OperationState {
+    // The operation has never been scheduled, or has been cancelled
+    Unset,
+    // The operation is scheduled and its timestamp has not passed yet
+    Waiting,
+    // The operation can be executed
+    Ready,
+    // The operation has been executed
+    Done,
+    // The grace period after the timestamp has passed without the operation being executed
+    Expired,
+}
 
-// Define the types of the contract's storage.
-type TxIdHashType = (SOLAddress, Uint<256>, SOLBytes, SOLBytes, Uint<256>);
+impl OperationState {
+    // The bitmap of states expected by `TimelockUnexpectedOperationState`, one bit per state
+    fn bitmap(states: &[OperationState]) -> FixedBytes<32> {
+        let bitmap = states.iter().fold(U256::ZERO, |bitmap, state| bitmap | (U256::from(1) << (*state as u8)));
+        FixedBytes::from(bitmap.to_be_bytes::<32>())
+    }
+}
 
+// The events and errors of OpenZeppelin's TimelockController, along with the ones for the maximum delay and grace period
 sol!{
-    error AlreadyInitialized();
-    error NotOwnerError();
-    error AlreadyQueuedError(bytes32 txId);
-    error TimestampNotInRangeError(uint256 blockTimestamp, uint256 timestamp);
-    error NotQueuedError(bytes32 txId);
-    error TimestampNotPassedError(uint256 blockTimestamp, uint256 timestamp);
-    error TimestampExpiredError(uint256 blockTimestamp, uint256 expiresAt);
-    error TxFailedError();
-
-    event Queue(
-        bytes32 indexed txId,
-        address indexed target,
-        uint256 value,
-        string func,
-        bytes data,
-        uint256 timestamp
-    );
-    event Execute(
-        bytes32 indexed txId,
-        address indexed target,
+    error TimelockInvalidOperationLength(uint256 targets, uint256 payloads, uint256 values);
+    error TimelockInsufficientDelay(uint256 delay, uint256 minDelay);
+    error TimelockExcessiveDelay(uint256 delay, uint256 maxDelay);
+    error TimelockUnexpectedOperationState(bytes32 operationId, bytes32 expectedStates);
+    error TimelockUnexecutedPredecessor(bytes32 predecessorId);
+    error TimelockUnauthorizedCaller(address caller);
+    error InvalidDelayError(uint256 minDelay, uint256 maxDelay);
+    error FailedCall();
+
+    event CallScheduled(
+        bytes32 indexed id,
+        uint256 indexed index,
+        address target,
         uint256 value,
-        string func,
         bytes data,
-        uint256 timestamp
+        bytes32 predecessor,
+        uint256 delay
     );
-    event Cancel(bytes32 indexed txId);
+    event CallExecuted(bytes32 indexed id, uint256 indexed index, address target, uint256 value, bytes data);
+    event CallSalt(bytes32 indexed id, bytes32 salt);
+    event Cancelled(bytes32 indexed id);
+    event MinDelayChange(uint256 oldDuration, uint256 newDuration);
+    event MaxDelayChange(uint256 oldDuration, uint256 newDuration);
+    event GracePeriodChange(uint256 oldDuration, uint256 newDuration);
 }
 
 // Define persistent storage using the Solidity ABI.
@@ -47,80 +101,210 @@ sol_storage! {
     // Define the contract's storage.
     #[entrypoint]
     pub struct TimeLock {
-        address owner;
-        mapping(bytes32 => bool) queued;
+        // Minimum delay between scheduling and executing an operation
+        uint256 min_delay;
+        // Maximum delay between scheduling and executing an operation
+        uint256 max_delay;
+        // Time after the timestamp during which an operation can still be executed
+        uint256 grace_period;
+        // The timestamp of every scheduled operation, or `DONE_TIMESTAMP` once executed
+        mapping(bytes32 => uint256) timestamps;
+        // Allows access_control to access TimeLock's storage
+        #[borrow]
+        AccessControl access_control;
     }
 }
 
 // Error types for the TimeLock contract
 #[derive(SolidityError)]
 pub enum TimeLockError {
-    // Error for when the contract is already initialized.
-    AlreadyInitialized(AlreadyInitialized),
-    // Error for when the sender is not the owner
-    NotOwnerError(NotOwnerError),
-    // Error for when the transaction is already queued
-    AlreadyQueuedError(AlreadyQueuedError),
-    // Error for when the timestamp is not in the range
-    TimestampNotInRangeError(TimestampNotInRangeError),
-    // Error for when the transaction is not queued
-    NotQueuedError(NotQueuedError),
-    // Error for when the timestamp has not yet passed
-    TimestampNotPassedError(TimestampNotPassedError),
-    // Error for when the timestamp has expired
-    TimestampExpiredError(TimestampExpiredError),
-    // Error for when a transaction fails
-    TxFailedError(TxFailedError),
+    // Error for when the sender does not have the required role
+    AccessControlUnauthorizedAccount(AccessControlUnauthorizedAccount),
+    // Error for when the arrays of a batch have different lengths
+    TimelockInvalidOperationLength(TimelockInvalidOperationLength),
+    // Error for when the delay of an operation is less than the minimum delay
+    TimelockInsufficientDelay(TimelockInsufficientDelay),
+    // Error for when the delay of an operation is greater than the maximum delay
+    TimelockExcessiveDelay(TimelockExcessiveDelay),
+    // Error for when an operation is not in one of the expected states
+    TimelockUnexpectedOperationState(TimelockUnexpectedOperationState),
+    // Error for when the predecessor of an operation has not been executed
+    TimelockUnexecutedPredecessor(TimelockUnexecutedPredecessor),
+    // Error for when a method that must go through the timelock is called directly
+    TimelockUnauthorizedCaller(TimelockUnauthorizedCaller),
+    // Error for when the minimum delay is greater than the maximum delay
+    InvalidDelayError(InvalidDelayError),
+    // Error for when a call fails
+    FailedCall(FailedCall),
 }
 
-// Marks `TimeLock` as a contract with the specified external methods
+// Marks `TimeLock` as a contract with the specified external methods.
+// The methods, events and errors of OpenZeppelin's TimelockController have the same selectors,
+// so governors and other tooling built for it can use this timelock.
 #[public]
+#[inherit(AccessControl)]
 impl TimeLock  {
 
-    // Minimum delay allowed for a transaction
-    pub const MIN_DELAY: u64 = 10;
-    // Maximum delay allowed for a transaction
-    pub const MAX_DELAY: u64 = 1000;
-    // Grace period after the maximum delay
-    pub const GRACE_PERIOD: u64 = 1000;
+    // The constructor sets up the roles and the delays, following OpenZeppelin's TimelockController:
+    // proposers are also granted the canceller role, the timelock administers itself,
+    // and the optional `admin` can grant roles until it renounces its admin role.
+    #[constructor]
+    pub fn constructor(
+        &mut self,
+        min_delay: U256, // Minimum delay between scheduling and executing an operation
+        max_delay: U256, // Maximum delay between scheduling and executing an operation
+        grace_period: U256, // Time after the timestamp during which an operation can still be executed
+        proposers: Vec<Address>, // Accounts granted the proposer and canceller roles
+        executors: Vec<Address>, // Accounts granted the executor role, the zero address lets anyone execute
+        admin: Address, // Optional admin, the zero address for none
+    ) -> Result<(), TimeLockError> {
+        check_delays(min_delay, max_delay)?;
 
-    pub fn initialize(&mut self) -> Result<(), TimeLockError> {
-        if self.owner.get() != Address::default() {
-            return Err(TimeLockError::AlreadyInitialized(AlreadyInitialized{}))
+        // The timelock administers itself, so role changes can go through scheduled operations
+        self.access_control._grant_role(DEFAULT_ADMIN_ROLE, self.vm().contract_address());
+        if admin != Address::ZERO {
+            self.access_control._grant_role(DEFAULT_ADMIN_ROLE, admin);
         }
-        self.owner.set(msg::sender());
+        for proposer in proposers {
+            self.access_control._grant_role(PROPOSER_ROLE, proposer);
+            self.access_control._grant_role(CANCELLER_ROLE, proposer);
+        }
+        for executor in executors {
+            self.access_control._grant_role(EXECUTOR_ROLE, executor);
+        }
+
+        self.set_min_delay(min_delay);
+        self.set_max_delay(max_delay);
+        self.set_grace_period(grace_period);
+        Ok(())
+    }
+
+    // The timelock can receive ETH, to be sent by the operations it executes
+    #[receive]
+    #[payable]
+    pub fn receive(&mut self) -> Result<(), Vec<u8>> {
         Ok(())
     }
 
-    pub fn owner(&self) -> Address {
-        self.owner.get()
+    // Role held by the accounts that can schedule operations
+    #[selector(name = "PROPOSER_ROLE")]
+    pub fn proposer_role(&self) -> FixedBytes<32> {
+        PROPOSER_ROLE
+    }
+
+    // Role held by the accounts that can execute operations
+    #[selector(name = "EXECUTOR_ROLE")]
+    pub fn executor_role(&self) -> FixedBytes<32> {
+        EXECUTOR_ROLE
+    }
+
+    // Role held by the accounts that can cancel operations
+    #[selector(name = "CANCELLER_ROLE")]
+    pub fn canceller_role(&self) -> FixedBytes<32> {
+        CANCELLER_ROLE
+    }
+
+    // Function to get the minimum delay between scheduling and executing an operation
+    pub fn get_min_delay(&self) -> U256 {
+        self.min_delay.get()
+    }
+
+    // Function to get the maximum delay between scheduling and executing an operation
+    pub fn get_max_delay(&self) -> U256 {
+        self.max_delay.get()
+    }
+
+    // Function to get the time after the timestamp during which an operation can still be executed
+    pub fn get_grace_period(&self) -> U256 {
+        self.grace_period.get()
     }
 
-    // Function to generate a transaction ID
-    pub fn get_tx_id(
-        &self, 
-        target: Address, // Target address for the transaction
+    // The delays can only be changed by the timelock itself,
+    // i.e. the change has to be scheduled and executed like any other operation
+
+    // Function to change the minimum delay
+    pub fn update_delay(&mut self, new_delay: U256) -> Result<(), TimeLockError> {
+        self.check_self_call()?;
+        check_delays(new_delay, self.max_delay.get())?;
+        self.set_min_delay(new_delay);
+        Ok(())
+    }
+
+    // Function to change the maximum delay
+    pub fn update_max_delay(&mut self, new_delay: U256) -> Result<(), TimeLockError> {
+        self.check_self_call()?;
+        check_delays(self.min_delay.get(), new_delay)?;
+        self.set_max_delay(new_delay);
+        Ok(())
+    }
+
+    // Function to change the grace period
+    pub fn update_grace_period(&mut self, new_grace_period: U256) -> Result<(), TimeLockError> {
+        self.check_self_call()?;
+        self.set_grace_period(new_grace_period);
+        Ok(())
+    }
+
+    // Function to generate the ID of an operation
+    pub fn hash_operation(
+        &self,
+        target: Address, // Target address of the call
         value: U256, // Value to be transferred
-        func: String, // Function name to be called
-        data: Bytes, // Data to be passed to the function
-        timestamp: U256, // Timestamp for the transaction
-    ) -> FixedBytes<32>{
-        
-        // Package the transaction data
-        let tx_hash_data = (target, value, func, data, timestamp);
-        // Encode the transaction data using ABI encoding
-        let tx_hash_bytes = TxIdHashType::abi_encode_sequence(&tx_hash_data);
-        // Initialize a new Keccak256 hasher
-        let mut hasher = Keccak256::new();
-        // Update the hasher with the encoded bytes
-        hasher.update(tx_hash_bytes);
-        // Finalize the hash computation
-        let result = hasher.finalize();
-        // Convert the hash result to a vector
-        let result_vec = result.to_vec();
-        // Create a FixedBytes<32> instance from the result vector
-        // This is used as the transaction ID
-        alloy_primitives::FixedBytes::<32>::from_slice(&result_vec)
+        data: Bytes, // Calldata of the call
+        predecessor: FixedBytes<32>, // ID of the operation that must be executed first, zero for none
+        salt: FixedBytes<32>, // Distinguishes otherwise identical operations
+    ) -> FixedBytes<32> {
+        // Encode the operation using ABI encoding and hash it
+        let operation_data = (target, value, data, predecessor, salt);
+        let operation_bytes = OperationHashType::abi_encode_params(&operation_data);
+        keccak(operation_bytes)
+    }
+
+    // Function to generate the ID of a batch operation
+    pub fn hash_operation_batch(
+        &self,
+        targets: Vec<Address>, // Target addresses of the calls
+        values: Vec<U256>, // Values to be transferred
+        payloads: Vec<Bytes>, // Calldata of the calls
+        predecessor: FixedBytes<32>, // ID of the operation that must be executed first, zero for none
+        salt: FixedBytes<32>, // Distinguishes otherwise identical operations
+    ) -> FixedBytes<32> {
+        // Encode the batch using ABI encoding and hash it
+        let operation_data = (targets, values, payloads, predecessor, salt);
+        let operation_bytes = BatchOperationHashType::abi_encode_params(&operation_data);
+        keccak(operation_bytes)
+    }
+
+    // Function to get the timestamp at which an operation becomes ready,
+    // zero if it is not scheduled and `DONE_TIMESTAMP` (1) once executed
+    pub fn get_timestamp(&self, id: FixedBytes<32>) -> U256 {
+        self.timestamps.get(id)
+    }
+
+    // Function to get the state of an operation:
+    // 0 = Unset, 1 = Waiting, 2 = Ready, 3 = Done, 4 = Expired
+    pub fn get_operation_state(&self, id: FixedBytes<32>) -> u8 {
+        self.operation_state(id) as u8
+    }
+
+    // Function to check if an operation has been scheduled, whatever its current state
+    pub fn is_operation(&self, id: FixedBytes<32>) -> bool {
+        self.operation_state(id) != OperationState::Unset
+    }
+
+    // Function to check if an operation is scheduled and not executed, cancelled or expired
+    pub fn is_operation_pending(&self, id: FixedBytes<32>) -> bool {
+        matches!(self.operation_state(id), OperationState::Waiting | OperationState::Ready)
+    }
+
+    // Function to check if an operation can be executed
+    pub fn is_operation_ready(&self, id: FixedBytes<32>) -> bool {
+        self.operation_state(id) == OperationState::Ready
+    }
+
+    // Function to check if an operation has been executed
+    pub fn is_operation_done(&self, id: FixedBytes<32>) -> bool {
+        self.operation_state(id) == OperationState::Done
     }
 
     // The `deposit` method is payable, so it can receive funds.
@@ -128,154 +312,534 @@ impl TimeLock  {
     pub fn deposit(&self) {
     }
 
-    // Function to queue a transaction for execution
-    pub fn queue(
+    // Function to schedule an operation, executable once `delay` seconds have passed
+    pub fn schedule(
         &mut self,
-        target: Address, // Target address for the transaction
+        target: Address, // Target address of the call
         value: U256, // Value to be transferred
-        func: String, // Function name to be called
-        data: Bytes, // Data to be passed to the function
-        timestamp: U256, // Timestamp for the transaction
+        data: Bytes, // Calldata of the call
+        predecessor: FixedBytes<32>, // ID of the operation that must be executed first, zero for none
+        salt: FixedBytes<32>, // Distinguishes otherwise identical operations
+        delay: U256, // Delay before the operation can be executed
     ) -> Result<(), TimeLockError> {
-        // Check if the caller is the owner of the contract
-        if self.owner.get() != msg::sender() {
-            // If not, return an error indicating the caller is not the owner
-            return Err(TimeLockError::NotOwnerError(NotOwnerError{}));
-        };
-        
-        // Calculate a transaction ID using the provided parameters
-        let tx_id = self.get_tx_id(target, value, func.clone(), data.clone(), timestamp);
-        // Check if the transaction is already queued
-        if self.queued.get(tx_id) {
-            return Err(TimeLockError::AlreadyQueuedError(AlreadyQueuedError{txId: tx_id.into()}));
-        }
+        // Check if the caller has the proposer role
+        self.access_control._check_role(PROPOSER_ROLE, self.vm().msg_sender())?;
 
-        // Check if the provided timestamp is within the allowed range
-        if timestamp < U256::from(block::timestamp()) + U256::from(TimeLock::MIN_DELAY)
-            || timestamp > U256::from(block::timestamp()) + U256::from(TimeLock::MAX_DELAY)
-        {
-            return Err(TimeLockError::TimestampNotInRangeError(TimestampNotInRangeError{blockTimestamp: U256::from(block::timestamp()),timestamp: timestamp}));
-        }
+        // Calculate the operation ID using the provided parameters
+        let id = self.hash_operation(target, value, data.clone(), predecessor, salt);
+        self._schedule(id, delay)?;
 
-        // Set the transaction as queued in the contract's state
-        let mut queue_id = self.queued.setter(tx_id);
-        queue_id.set(true);
-        // Log the Queue event
-        evm::log(Queue {
-            txId: tx_id.into(),
+        // Log the CallScheduled event, and the salt if there is one
+        log(self.vm(), CallScheduled {
+            id,
+            index: U256::ZERO,
             target,
-            value: value,
-            func: func,
+            value,
             data: data.to_vec().into(),
-            timestamp: timestamp,
+            predecessor,
+            delay,
         });
-        // If all checks pass and the transaction is successfully queued, return Ok
+        self.log_salt(id, salt);
+        // If all checks pass and the operation is successfully scheduled, return Ok
         Ok(())
     }
 
-    // Function to execute a queued transaction
+    // Function to schedule a batch operation, whose calls are executed together under a single ID
+    pub fn schedule_batch(
+        &mut self,
+        targets: Vec<Address>, // Target addresses of the calls
+        values: Vec<U256>, // Values to be transferred
+        payloads: Vec<Bytes>, // Calldata of the calls
+        predecessor: FixedBytes<32>, // ID of the operation that must be executed first, zero for none
+        salt: FixedBytes<32>, // Distinguishes otherwise identical operations
+        delay: U256, // Delay before the operation can be executed
+    ) -> Result<(), TimeLockError> {
+        // Check if the caller has the proposer role
+        self.access_control._check_role(PROPOSER_ROLE, self.vm().msg_sender())?;
+        check_batch_length(&targets, &values, &payloads)?;
+
+        // Calculate the operation ID using the provided parameters
+        let id = self.hash_operation_batch(targets.clone(), values.clone(), payloads.clone(), predecessor, salt);
+        self._schedule(id, delay)?;
+
+        // Log a CallScheduled event for every call of the batch
+        for (index, ((target, value), data)) in targets.into_iter().zip(values).zip(payloads).enumerate() {
+            log(self.vm(), CallScheduled {
+                id,
+                index: U256::from(index),
+                target,
+                value,
+                data: data.to_vec().into(),
+                predecessor,
+                delay,
+            });
+        }
+        self.log_salt(id, salt);
+        Ok(())
+    }
+
+    // Function to cancel a scheduled operation
+    pub fn cancel(&mut self, id: FixedBytes<32>) -> Result<(), TimeLockError> {
+        // Check if the caller has the canceller role
+        self.access_control._check_role(CANCELLER_ROLE, self.vm().msg_sender())?;
+
+        // Check if the operation is pending
+        if !self.is_operation_pending(id) {
+            return Err(TimeLockError::TimelockUnexpectedOperationState(TimelockUnexpectedOperationState{
+                operationId: id,
+                expectedStates: OperationState::bitmap(&[OperationState::Waiting, OperationState::Ready]),
+            }));
+        }
+
+        // Set the operation as not scheduled in the contract's state
+        self.timestamps.setter(id).set(U256::ZERO);
+
+        // Log the operation cancellation
+        log(self.vm(), Cancelled {
+            id,
+        });
+
+        // Return Ok if the operation is successfully cancelled
+        Ok(())
+    }
+
+    // Function to execute a ready operation
+    #[payable]
     pub fn execute(
         &mut self,
-        target: Address, // Target address for the transaction
+        target: Address, // Target address of the call
         value: U256, // Value to be transferred
-        func: String, // Function name to be called
-        data: Bytes, // Data to be passed to the function
-        timestamp: U256, // Timestamp for the transaction
+        payload: Bytes, // Calldata of the call
+        predecessor: FixedBytes<32>, // ID of the operation that must be executed first, zero for none
+        salt: FixedBytes<32>, // Distinguishes otherwise identical operations
     ) -> Result<(), TimeLockError> {
-        // Check if the caller is the owner of the contract
-        if self.owner.get() != msg::sender() {
-            // If not, return an error indicating the caller is not the owner
-            return Err(TimeLockError::NotOwnerError(NotOwnerError{}));
-        };
-        
-        // Calculate a transaction ID using the provided parameters
-        let tx_id = self.get_tx_id(target, value, func.clone(), data.clone(), timestamp);
-        // Check if the transaction is not queued
-        if !self.queued.get(tx_id) {
-            return Err(TimeLockError::NotQueuedError(NotQueuedError{txId: tx_id.into()}));
+        // Check if the caller has the executor role, unless the role is open to anyone
+        self.check_executor()?;
+
+        // Calculate the operation ID using the provided parameters
+        let id = self.hash_operation(target, value, payload.clone(), predecessor, salt);
+        self.before_call(id, predecessor)?;
+
+        // Call the target contract and log the CallExecuted event
+        self.call_target(id, 0, target, value, payload)
+    }
+
+    // Function to execute a ready batch operation, reverting all of its calls if any fails
+    #[payable]
+    pub fn execute_batch(
+        &mut self,
+        targets: Vec<Address>, // Target addresses of the calls
+        values: Vec<U256>, // Values to be transferred
+        payloads: Vec<Bytes>, // Calldata of the calls
+        predecessor: FixedBytes<32>, // ID of the operation that must be executed first, zero for none
+        salt: FixedBytes<32>, // Distinguishes otherwise identical operations
+    ) -> Result<(), TimeLockError> {
+        // Check if the caller has the executor role, unless the role is open to anyone
+        self.check_executor()?;
+        check_batch_length(&targets, &values, &payloads)?;
+
+        // Calculate the operation ID using the provided parameters
+        let id = self.hash_operation_batch(targets.clone(), values.clone(), payloads.clone(), predecessor, salt);
+        self.before_call(id, predecessor)?;
+
+        // Call every target contract in order and log a CallExecuted event for each
+        for (index, ((target, value), payload)) in targets.into_iter().zip(values).zip(payloads).enumerate() {
+            self.call_target(id, index, target, value, payload)?;
+        }
+        Ok(())
+    }
+}
+
+impl TimeLock {
+    // Fails unless the caller is the timelock itself
+    fn check_self_call(&self) -> Result<(), TimeLockError> {
+        if self.vm().msg_sender() != self.vm().contract_address() {
+            return Err(TimeLockError::TimelockUnauthorizedCaller(TimelockUnauthorizedCaller{caller: self.vm().msg_sender()}));
+        }
+        Ok(())
+    }
+
+    // Sets the minimum delay and logs the change
+    fn set_min_delay(&mut self, new_delay: U256) {
+        log(self.vm(), MinDelayChange {
+            oldDuration: self.min_delay.get(),
+            newDuration: new_delay,
+        });
+        self.min_delay.set(new_delay);
+    }
+
+    // Sets the maximum delay and logs the change
+    fn set_max_delay(&mut self, new_delay: U256) {
+        log(self.vm(), MaxDelayChange {
+            oldDuration: self.max_delay.get(),
+            newDuration: new_delay,
+        });
+        self.max_delay.set(new_delay);
+    }
+
+    // Sets the grace period and logs the change
+    fn set_grace_period(&mut self, new_grace_period: U256) {
+        log(self.vm(), GracePeriodChange {
+            oldDuration: self.grace_period.get(),
+            newDuration: new_grace_period,
+        });
+        self.grace_period.set(new_grace_period);
+    }
+
+    // Derives the state of an operation from its timestamp
+    fn operation_state(&self, id: FixedBytes<32>) -> OperationState {
+        let timestamp = self.timestamps.get(id);
+        let now = U256::from(self.vm().block_timestamp());
+        if timestamp == U256::ZERO {
+            OperationState::Unset
+        } else if timestamp == DONE_TIMESTAMP {
+            OperationState::Done
+        } else if now < timestamp {
+            OperationState::Waiting
+        } else if now > timestamp.saturating_add(self.grace_period.get()) {
+            OperationState::Expired
+        } else {
+            OperationState::Ready
+        }
+    }
+
+    // Checks that the caller has the executor role, unless the role is open to anyone
+    fn check_executor(&self) -> Result<(), TimeLockError> {
+        if !self.access_control.has_role(EXECUTOR_ROLE, Address::ZERO) {
+            self.access_control._check_role(EXECUTOR_ROLE, self.vm().msg_sender())?;
+        }
+        Ok(())
+    }
+
+    // Marks an operation as scheduled, executable once `delay` seconds have passed
+    fn _schedule(&mut self, id: FixedBytes<32>, delay: U256) -> Result<(), TimeLockError> {
+        // Check if the operation is already scheduled or executed
+        if self.is_operation(id) {
+            return Err(TimeLockError::TimelockUnexpectedOperationState(TimelockUnexpectedOperationState{
+                operationId: id,
+                expectedStates: OperationState::bitmap(&[OperationState::Unset]),
+            }));
+        }
+
+        // Check if the provided delay is within the allowed range
+        if delay < self.min_delay.get() {
+            return Err(TimeLockError::TimelockInsufficientDelay(TimelockInsufficientDelay{delay, minDelay: self.min_delay.get()}));
+        }
+        if delay > self.max_delay.get() {
+            return Err(TimeLockError::TimelockExcessiveDelay(TimelockExcessiveDelay{delay, maxDelay: self.max_delay.get()}));
+        }
+
+        // Set the operation as scheduled in the contract's state
+        let timestamp = U256::from(self.vm().block_timestamp()).saturating_add(delay);
+        self.timestamps.setter(id).set(timestamp);
+        Ok(())
+    }
+
+    // Logs the salt of an operation, unless it is zero
+    fn log_salt(&self, id: FixedBytes<32>, salt: FixedBytes<32>) {
+        if salt != FixedBytes::ZERO {
+            log(self.vm(), CallSalt {
+                id,
+                salt,
+            });
         }
-        
+    }
+
+    // Checks that an operation can be executed and marks it as executed
+    fn before_call(&mut self, id: FixedBytes<32>, predecessor: FixedBytes<32>) -> Result<(), TimeLockError> {
         // ----|-------------------|-------
         //  timestamp    timestamp + grace period
-
-        // Check if the timestamp has passed
-        if U256::from(block::timestamp()) < timestamp {
-            return Err(TimeLockError::TimestampNotPassedError(TimestampNotPassedError{blockTimestamp: U256::from(block::timestamp()), timestamp: timestamp}));
+        if !self.is_operation_ready(id) {
+            return Err(TimeLockError::TimelockUnexpectedOperationState(TimelockUnexpectedOperationState{
+                operationId: id,
+                expectedStates: OperationState::bitmap(&[OperationState::Ready]),
+            }));
         }
-        
-        // Check if the timestamp has expired
-        if U256::from(block::timestamp()) > timestamp + U256::from(TimeLock::GRACE_PERIOD) {
-            return Err(TimeLockError::TimestampExpiredError(TimestampExpiredError{blockTimestamp: U256::from(block::timestamp()), expiresAt: timestamp + U256::from(TimeLock::GRACE_PERIOD)}));
+
+        // Check if the predecessor has been executed
+        if predecessor != FixedBytes::ZERO && !self.is_operation_done(predecessor) {
+            return Err(TimeLockError::TimelockUnexecutedPredecessor(TimelockUnexecutedPredecessor{predecessorId: predecessor}));
         }
-        
-        // Set the transaction as not queued in the contract's state
-        let mut queue_id = self.queued.setter(tx_id);
-        queue_id.set(false);
-
-        // Clone the data variable to ensure its lifetime is long enough
-        // let cloned_data: Vec<u8> = data.clone().into();
-        
-        // Prepare calldata
-        let mut hasher = Keccak256::new();
-        hasher.update(func.as_bytes());
-        // Get function selector
-        let hashed_function_selector = hasher.finalize();
-        // Combine function selector and input data
-        let calldata = [&hashed_function_selector[..4], &data].concat();
-        
+
+        // Set the operation as executed in the contract's state before making any call
+        self.timestamps.setter(id).set(DONE_TIMESTAMP);
+        Ok(())
+    }
+
+    // Calls a target contract and logs the CallExecuted event
+    fn call_target(&mut self, id: FixedBytes<32>, index: usize, target: Address, value: U256, payload: Bytes) -> Result<(), TimeLockError> {
         // Call the target contract with the provided parameters
-        match call(Call::new_in(self).value(value), target, &calldata) {
-            // Log the transaction execution if successful
+        let result = self.vm().call(&ValueCall(value), target, &payload);
+        match result {
+            // Log the call execution if successful
             Ok(_) => {
-                evm::log(Execute {
-                    txId: tx_id.into(),
+                log(self.vm(), CallExecuted {
+                    id,
+                    index: U256::from(index),
                     target,
-                    value: value,
-                    func: func,
-                    data: data.to_vec().into(),
-                    timestamp: timestamp,
+                    value,
+                    data: payload.to_vec().into(),
                 });
                 Ok(())
             },
-            // Return an error if the transaction fails
-            Err(_) => Err(TimeLockError::TxFailedError(TxFailedError{})),
+            // Return an error if the call fails
+            Err(_) => Err(TimeLockError::FailedCall(FailedCall{})),
         }
     }
+}
 
-    // Function to cancel a queued transaction
-    pub fn cancel(
-        &mut self,
-        target: Address,
-        value: U256,
-        func: String,
-        data: Bytes,
-        timestamp: U256,
-    ) -> Result<(), TimeLockError> {
-        // Check if the caller is the owner of the contract
-        if self.owner.get() != msg::sender() {
-            // If not, return an error indicating the caller is not the owner
-            return Err(TimeLockError::NotOwnerError(NotOwnerError{}));
+// The context of the calls made by `execute` and `execute_batch`, which only sets the value sent.
+// `Call::new_in` would borrow the contract mutably while `self.vm()` borrows it to make the call.
+struct ValueCall(U256);
+
+impl CallContext for ValueCall {
+    fn gas(&self) -> u64 {
+        u64::MAX
+    }
+}
+
+// SAFETY: `MutatingCallContext` holds the contract to prevent aliasing its storage when it is reentered.
+// `call_target` holds no storage values across the call, and the host flushes and clears the storage cache before calling.
+unsafe impl MutatingCallContext for ValueCall {
+    fn value(&self) -> U256 {
+        self.0
+    }
+}
+
+// Checks that the arrays of a batch have the same length as the targets
+fn check_batch_length(targets: &[Address], values: &[U256], payloads: &[Bytes]) -> Result<(), TimeLockError> {
+    if values.len() != targets.len() || payloads.len() != targets.len() {
+        return Err(TimeLockError::TimelockInvalidOperationLength(TimelockInvalidOperationLength{
+            targets: U256::from(targets.len()),
+            payloads: U256::from(payloads.len()),
+            values: U256::from(values.len()),
+        }));
+    }
+    Ok(())
+}
+
+// Checks that the minimum delay is not greater than the maximum delay
+fn check_delays(min_delay: U256, max_delay: U256) -> Result<(), TimeLockError> {
+    if min_delay > max_delay {
+        return Err(TimeLockError::InvalidDelayError(InvalidDelayError{minDelay: min_delay, maxDelay: max_delay}));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy_primitives::address;
+    use stylus_sdk::testing::*;
+
+    const PROPOSER: Address = address!("0000000000000000000000000000000000000001");
+    const EXECUTOR: Address = address!("0000000000000000000000000000000000000002");
+
+    fn deploy(vm: &TestVM) -> TimeLock {
+        let mut contract = TimeLock::from(vm);
+        let result = contract.constructor(U256::from(10), U256::from(1000), U256::from(100), vec![PROPOSER], vec![EXECUTOR], Address::ZERO);
+        assert!(result.is_ok());
+        contract
+    }
+
+    #[test]
+    fn test_constructor() {
+        let vm = TestVM::default();
+        let contract = deploy(&vm);
+
+        assert_eq!(contract.get_min_delay(), U256::from(10));
+        assert_eq!(contract.get_max_delay(), U256::from(1000));
+        assert_eq!(contract.get_grace_period(), U256::from(100));
+        assert!(contract.access_control.has_role(DEFAULT_ADMIN_ROLE, vm.contract_address()));
+        assert!(contract.access_control.has_role(PROPOSER_ROLE, PROPOSER));
+        assert!(contract.access_control.has_role(CANCELLER_ROLE, PROPOSER));
+        assert!(contract.access_control.has_role(EXECUTOR_ROLE, EXECUTOR));
+
+        // The minimum delay cannot exceed the maximum delay
+        let mut contract = TimeLock::from(&vm);
+        assert!(contract
+            .constructor(U256::from(1000), U256::from(10), U256::from(100), vec![], vec![], Address::ZERO)
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_delays() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+
+        // Only the timelock itself can change the delays
+        assert!(contract.update_delay(U256::from(20)).is_err());
+        assert!(contract.update_max_delay(U256::from(2000)).is_err());
+        assert!(contract.update_grace_period(U256::from(200)).is_err());
+
+        vm.set_sender(vm.contract_address());
+        assert!(contract.update_delay(U256::from(20)).is_ok());
+        assert!(contract.update_max_delay(U256::from(2000)).is_ok());
+        assert!(contract.update_grace_period(U256::from(200)).is_ok());
+        assert_eq!(contract.get_min_delay(), U256::from(20));
+        assert_eq!(contract.get_max_delay(), U256::from(2000));
+        assert_eq!(contract.get_grace_period(), U256::from(200));
+
+        // The minimum delay cannot exceed the maximum delay
+        assert!(contract.update_delay(U256::from(3000)).is_err());
+        assert!(contract.update_max_delay(U256::from(10)).is_err());
+    }
+
+    #[test]
+    fn test_operation_state() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+        vm.set_block_timestamp(1000);
+
+        let target = address!("0000000000000000000000000000000000000003");
+        let schedule = |contract: &mut TimeLock, delay: U256| {
+            contract.schedule(target, U256::ZERO, Bytes::from(vec![]), FixedBytes::ZERO, FixedBytes::ZERO, delay)
         };
+        let id = contract.hash_operation(target, U256::ZERO, Bytes::from(vec![]), FixedBytes::ZERO, FixedBytes::ZERO);
 
-        // Calculate a transaction ID using the provided parameters
-        let tx_id = self.get_tx_id(target, value, func, data, timestamp);
-        // Check if the transaction is not queued
-        if !self.queued.get(tx_id) {
-            return Err(TimeLockError::NotQueuedError(NotQueuedError{txId: tx_id.into()}));
-        }
+        // Only proposers can schedule, and only within the delays
+        assert!(matches!(schedule(&mut contract, U256::from(500)), Err(TimeLockError::AccessControlUnauthorizedAccount(_))));
+        vm.set_sender(PROPOSER);
+        assert!(matches!(schedule(&mut contract, U256::from(5)), Err(TimeLockError::TimelockInsufficientDelay(_))));
+        assert!(matches!(schedule(&mut contract, U256::from(1500)), Err(TimeLockError::TimelockExcessiveDelay(_))));
+
+        assert!(schedule(&mut contract, U256::from(500)).is_ok());
+        assert_eq!(contract.get_timestamp(id), U256::from(1500));
+        assert_eq!(contract.get_operation_state(id), OperationState::Waiting as u8);
+        assert!(matches!(schedule(&mut contract, U256::from(500)), Err(TimeLockError::TimelockUnexpectedOperationState(_))));
+        vm.set_block_timestamp(1500);
+        assert_eq!(contract.get_operation_state(id), OperationState::Ready as u8);
+        vm.set_block_timestamp(1601);
+        assert_eq!(contract.get_operation_state(id), OperationState::Expired as u8);
+    }
 
-        // Set the transaction as not queued in the contract's state
-        let mut queue_id = self.queued.setter(tx_id);
-        queue_id.set(false);
+    #[test]
+    fn test_cancel() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+        vm.set_block_timestamp(1000);
+        vm.set_sender(PROPOSER);
 
-        // Log the transaction cancellation
-        evm::log(Cancel {
-            txId: tx_id.into(),
-        });
+        let target = address!("0000000000000000000000000000000000000003");
+        let salt = FixedBytes::from(U256::from(1).to_be_bytes::<32>());
+        assert!(contract.schedule_batch(vec![target], vec![U256::ZERO], vec![], FixedBytes::ZERO, salt, U256::from(500)).is_err());
+        assert!(contract.schedule_batch(vec![target], vec![U256::ZERO], vec![Bytes::from(vec![])], FixedBytes::ZERO, salt, U256::from(500)).is_ok());
+        let id = contract.hash_operation_batch(vec![target], vec![U256::ZERO], vec![Bytes::from(vec![])], FixedBytes::ZERO, salt);
+        assert!(contract.is_operation_pending(id));
 
-        // Return Ok if the transaction is successfully cancelled
-        Ok(())
+        // Only cancellers can cancel, and only pending operations
+        vm.set_sender(EXECUTOR);
+        assert!(matches!(contract.cancel(id), Err(TimeLockError::AccessControlUnauthorizedAccount(_))));
+        vm.set_sender(PROPOSER);
+        assert!(contract.cancel(id).is_ok());
+        assert!(!contract.is_operation(id));
+        assert!(matches!(
+            contract.cancel(id),
+            Err(TimeLockError::TimelockUnexpectedOperationState(TimelockUnexpectedOperationState { expectedStates, .. }))
+                if expectedStates == FixedBytes::from(U256::from(6).to_be_bytes::<32>())
+        ));
+    }
+
+    #[test]
+    fn test_execute() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+        vm.set_block_timestamp(1000);
+        vm.set_sender(PROPOSER);
+
+        let target = address!("0000000000000000000000000000000000000003");
+        let data = Bytes::from(vec![0x12, 0x34]);
+        assert!(contract.schedule(target, U256::ZERO, data.clone(), FixedBytes::ZERO, FixedBytes::ZERO, U256::from(500)).is_ok());
+        let id = contract.hash_operation(target, U256::ZERO, data.clone(), FixedBytes::ZERO, FixedBytes::ZERO);
+
+        // Only executors can execute, and only ready operations
+        vm.set_sender(EXECUTOR);
+        assert!(matches!(
+            contract.execute(target, U256::ZERO, data.clone(), FixedBytes::ZERO, FixedBytes::ZERO),
+            Err(TimeLockError::TimelockUnexpectedOperationState(_))
+        ));
+        vm.set_block_timestamp(1500);
+        vm.set_sender(PROPOSER);
+        assert!(matches!(
+            contract.execute(target, U256::ZERO, data.clone(), FixedBytes::ZERO, FixedBytes::ZERO),
+            Err(TimeLockError::AccessControlUnauthorizedAccount(_))
+        ));
+
+        vm.set_sender(EXECUTOR);
+        vm.mock_call(target, data.to_vec(), Ok(vec![]));
+        assert!(contract.execute(target, U256::ZERO, data.clone(), FixedBytes::ZERO, FixedBytes::ZERO).is_ok());
+        assert!(contract.is_operation_done(id));
+        let logs = vm.get_emitted_logs();
+        let (topics, _) = logs.last().unwrap();
+        assert_eq!(topics[0], CallExecuted::SIGNATURE_HASH);
+        assert_eq!(topics[1], id);
+
+        // An operation can only be executed once
+        assert!(matches!(
+            contract.execute(target, U256::ZERO, data, FixedBytes::ZERO, FixedBytes::ZERO),
+            Err(TimeLockError::TimelockUnexpectedOperationState(_))
+        ));
     }
 
-    
-}
\ No newline at end of file
+    #[test]
+    fn test_execute_batch() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+        vm.set_block_timestamp(1000);
+        vm.set_sender(PROPOSER);
+
+        let targets = vec![address!("0000000000000000000000000000000000000003"), address!("0000000000000000000000000000000000000004")];
+        let values = vec![U256::ZERO, U256::ZERO];
+        let payloads = vec![Bytes::from(vec![0x01]), Bytes::from(vec![0x02])];
+        let salt = FixedBytes::from(U256::from(1).to_be_bytes::<32>());
+        assert!(contract.schedule_batch(targets.clone(), values.clone(), payloads.clone(), FixedBytes::ZERO, FixedBytes::ZERO, U256::from(500)).is_ok());
+        assert!(contract.schedule_batch(targets.clone(), values.clone(), payloads.clone(), FixedBytes::ZERO, salt, U256::from(500)).is_ok());
+        vm.set_block_timestamp(1500);
+        vm.set_sender(EXECUTOR);
+
+        // The arrays of a batch must have the same length
+        assert!(matches!(
+            contract.execute_batch(targets.clone(), vec![U256::ZERO], payloads.clone(), FixedBytes::ZERO, FixedBytes::ZERO),
+            Err(TimeLockError::TimelockInvalidOperationLength(_))
+        ));
+
+        // The batch fails if any of its calls fails
+        vm.mock_call(targets[1], payloads[1].to_vec(), Err(vec![]));
+        assert!(matches!(
+            contract.execute_batch(targets.clone(), values.clone(), payloads.clone(), FixedBytes::ZERO, FixedBytes::ZERO),
+            Err(TimeLockError::FailedCall(_))
+        ));
+
+        vm.mock_call(targets[1], payloads[1].to_vec(), Ok(vec![]));
+        assert!(contract.execute_batch(targets.clone(), values.clone(), payloads.clone(), FixedBytes::ZERO, salt).is_ok());
+        let id = contract.hash_operation_batch(targets, values, payloads, FixedBytes::ZERO, salt);
+        assert!(contract.is_operation_done(id));
+        let executed = vm.get_emitted_logs().into_iter().filter(|(topics, _)| topics[0] == CallExecuted::SIGNATURE_HASH && topics[1] == id).count();
+        assert_eq!(executed, 2);
+    }
+
+    #[test]
+    fn test_predecessor() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+        vm.set_block_timestamp(1000);
+        vm.set_sender(PROPOSER);
+
+        let target = address!("0000000000000000000000000000000000000003");
+        let first = Bytes::from(vec![0x01]);
+        let second = Bytes::from(vec![0x02]);
+        let predecessor = contract.hash_operation(target, U256::ZERO, first.clone(), FixedBytes::ZERO, FixedBytes::ZERO);
+        assert!(contract.schedule(target, U256::ZERO, first.clone(), FixedBytes::ZERO, FixedBytes::ZERO, U256::from(500)).is_ok());
+        assert!(contract.schedule(target, U256::ZERO, second.clone(), predecessor, FixedBytes::ZERO, U256::from(500)).is_ok());
+        vm.set_block_timestamp(1500);
+        vm.set_sender(EXECUTOR);
+
+        // The operation cannot be executed before its predecessor
+        assert!(matches!(
+            contract.execute(target, U256::ZERO, second.clone(), predecessor, FixedBytes::ZERO),
+            Err(TimeLockError::TimelockUnexecutedPredecessor(TimelockUnexecutedPredecessor { predecessorId })) if predecessorId == predecessor
+        ));
+        assert!(contract.execute(target, U256::ZERO, first, FixedBytes::ZERO, FixedBytes::ZERO).is_ok());
+        assert!(contract.execute(target, U256::ZERO, second, predecessor, FixedBytes::ZERO).is_ok());
+    }
+}
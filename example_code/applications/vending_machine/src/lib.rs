@@ -8,66 +8,299 @@
 //!
 
 // Allow `cargo stylus export-abi` to generate a main function if the "export-abi" feature is enabled.
-#![cfg_attr(not(feature = "export-abi"), no_main)]
+#![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 extern crate alloc;
 
-use alloy_primitives::{Address, Uint};
+// Modules and imports
+mod erc20;
+
+use alloy_primitives::Address;
+use alloy_sol_types::sol;
 // Import items from the SDK. The prelude contains common traits and macros.
 use stylus_sdk::alloy_primitives::U256;
 use stylus_sdk::prelude::*;
-use stylus_sdk::{block, console};
+use crate::erc20::{Erc20, Erc20Params};
+
+/// Cupcakes are an ERC-20 token, minted when the vending machine dispenses them.
+pub struct CupcakeParams;
+impl Erc20Params for CupcakeParams {
+    const NAME: &'static str = "Cupcake";
+    const SYMBOL: &'static str = "CUPCAKE";
+    const DECIMALS: u8 = 0;
+}
+
+// Define the errors for the contract.
+sol! {
+    // The sender is not the owner of the vending machine.
+    error NotOwner();
+    // The user has to wait until `availableAt` before getting another cupcake.
+    error CooldownActive(uint256 availableAt);
+    // The vending machine only has `available` cupcakes left.
+    error OutOfStock(uint256 available);
+    // The ETH sent does not match the price of the cupcakes, `expected`.
+    error IncorrectPayment(uint256 expected);
+    // The withdrawal of the sales failed.
+    error TransferFailed(bytes reason);
+}
+
+#[derive(SolidityError)]
+pub enum VendingMachineError {
+    NotOwner(NotOwner),
+    CooldownActive(CooldownActive),
+    OutOfStock(OutOfStock),
+    IncorrectPayment(IncorrectPayment),
+    TransferFailed(TransferFailed),
+}
 
 // Define persistent storage using the Solidity ABI.
 // `VendingMachine` will be the entrypoint for the contract.
 sol_storage! {
     #[entrypoint]
     pub struct VendingMachine {
-        // Mapping from user addresses to their cupcake balances.
-        mapping(address => uint256) cupcake_balances;
+        // The owner, who sets the prices, restocks and withdraws the sales.
+        address owner;
+        // The number of seconds a user has to wait between cupcakes.
+        uint256 cooldown;
+        // The price of a cupcake in wei.
+        uint256 price;
+        // The number of cupcakes left in the vending machine.
+        uint256 stock;
         // Mapping from user addresses to the last time they received a cupcake.
         mapping(address => uint256) cupcake_distribution_times;
+        // The cupcake token, which holds the cupcake balances.
+        #[borrow]
+        Erc20<CupcakeParams> erc20;
     }
 }
 
 // Declare that `VendingMachine` is a contract with the following external methods.
+// It inherits the ERC-20 methods of the cupcake token, so `balanceOf` returns the cupcake balance of a user.
 #[public]
+#[inherit(Erc20<CupcakeParams>)]
 impl VendingMachine {
-    // Give a cupcake to the specified user if they are eligible (i.e., if at least 5 seconds have passed since their last cupcake).
-    pub fn give_cupcake_to(&mut self, user_address: Address) -> bool {
-        // Get the last distribution time for the user.
-        let last_distribution = self.cupcake_distribution_times.get(user_address);
-        // Calculate the earliest next time the user can receive a cupcake.
-        let five_seconds_from_last_distribution = last_distribution + U256::from(5);
+    // The constructor sets the owner as the EOA that deployed the contract, the cooldown, the price and the initial stock.
+    #[constructor]
+    pub fn constructor(&mut self, cooldown: U256, price: U256, stock: U256) {
+        // Use tx_origin instead of msg_sender because we use a factory contract in deployment.
+        let owner = self.vm().tx_origin();
+        self.owner.set(owner);
+        self.cooldown.set(cooldown);
+        self.price.set(price);
+        self.stock.set(stock);
+    }
+
+    // As the owner, give a free cupcake to the specified user if they are eligible (i.e., if the cooldown has passed since their last cupcake).
+    pub fn give_cupcake_to(&mut self, user_address: Address) -> Result<bool, VendingMachineError> {
+        self.only_owner()?;
+        self.dispense(user_address, U256::from(1))?;
+        Ok(true)
+    }
 
+    // Buy `amount` cupcakes for the sender, paying the price of each of them in ETH.
+    #[payable]
+    pub fn buy_cupcakes(&mut self, amount: U256) -> Result<(), VendingMachineError> {
+        // Check that the payment matches the price of the cupcakes.
+        // An amount whose price overflows is more than the vending machine could ever stock.
+        let Some(expected) = self.price.get().checked_mul(amount) else {
+            return Err(VendingMachineError::OutOfStock(OutOfStock { available: self.stock.get() }));
+        };
+        if self.vm().msg_value() != expected {
+            return Err(VendingMachineError::IncorrectPayment(IncorrectPayment { expected }));
+        }
+        self.dispense(self.vm().msg_sender(), amount)
+    }
+
+    // Add `amount` cupcakes to the stock of the vending machine.
+    pub fn restock(&mut self, amount: U256) -> Result<(), VendingMachineError> {
+        self.only_owner()?;
+        let stock = self.stock.get();
+        self.stock.set(stock + amount);
+        Ok(())
+    }
+
+    // Set the number of seconds a user has to wait between cupcakes.
+    pub fn set_cooldown(&mut self, cooldown: U256) -> Result<(), VendingMachineError> {
+        self.only_owner()?;
+        self.cooldown.set(cooldown);
+        Ok(())
+    }
+
+    // Set the price of a cupcake in wei.
+    pub fn set_price(&mut self, price: U256) -> Result<(), VendingMachineError> {
+        self.only_owner()?;
+        self.price.set(price);
+        Ok(())
+    }
+
+    // Send the ETH paid for cupcakes to the owner.
+    pub fn withdraw(&mut self) -> Result<(), VendingMachineError> {
+        self.only_owner()?;
+        let balance = self.vm().balance(self.vm().contract_address());
+        self.vm()
+            .transfer_eth(self.owner.get(), balance)
+            .map_err(|reason| VendingMachineError::TransferFailed(TransferFailed { reason: reason.into() }))
+    }
+
+    // Get the owner of the vending machine.
+    pub fn owner(&self) -> Address {
+        self.owner.get()
+    }
+
+    // Get the number of seconds a user has to wait between cupcakes.
+    pub fn cooldown(&self) -> U256 {
+        self.cooldown.get()
+    }
+
+    // Get the price of a cupcake in wei.
+    pub fn price(&self) -> U256 {
+        self.price.get()
+    }
+
+    // Get the number of cupcakes left in the vending machine.
+    pub fn stock(&self) -> U256 {
+        self.stock.get()
+    }
+}
+
+// These methods aren't exposed to other contracts.
+impl VendingMachine {
+    // Check that the sender is the owner.
+    fn only_owner(&self) -> Result<(), VendingMachineError> {
+        if self.vm().msg_sender() != self.owner.get() {
+            return Err(VendingMachineError::NotOwner(NotOwner {}));
+        }
+        Ok(())
+    }
+
+    // Give `amount` cupcakes from the stock to the user, if the cooldown has passed since their last cupcake.
+    fn dispense(&mut self, user_address: Address, amount: U256) -> Result<(), VendingMachineError> {
+        // Calculate the earliest next time the user can receive a cupcake.
+        let last_distribution = self.cupcake_distribution_times.get(user_address);
+        let available_at = last_distribution + self.cooldown.get();
         // Get the current block timestamp.
-        let current_time = block::timestamp();
+        let current_time = U256::from(self.vm().block_timestamp());
         // Check if the user can receive a cupcake.
-        let user_can_receive_cupcake =
-            five_seconds_from_last_distribution <= Uint::<256, 4>::from(current_time);
-
-        if user_can_receive_cupcake {
-            // Increment the user's cupcake balance.
-            let mut balance_accessor = self.cupcake_balances.setter(user_address);
-            let balance = balance_accessor.get() + U256::from(1);
-            balance_accessor.set(balance);
-
-            // Update the distribution time to the current time.
-            let mut time_accessor = self.cupcake_distribution_times.setter(user_address);
-            let new_distribution_time = block::timestamp();
-            time_accessor.set(Uint::<256, 4>::from(new_distribution_time));
-            return true;
-        } else {
-            // User must wait before receiving another cupcake.
-            console!(
-                "HTTP 429: Too Many Cupcakes (you must wait at least 5 seconds between cupcakes)"
-            );
-            return false;
+        if current_time < available_at {
+            return Err(VendingMachineError::CooldownActive(CooldownActive { availableAt: available_at }));
         }
+
+        // Check if there are enough cupcakes left.
+        let stock = self.stock.get();
+        if stock < amount {
+            return Err(VendingMachineError::OutOfStock(OutOfStock { available: stock }));
+        }
+        self.stock.set(stock - amount);
+
+        // Mint the cupcakes to the user. Minting cannot fail, it only returns a `Result` like the other `Erc20` methods.
+        let _ = self.erc20.mint(user_address, amount);
+
+        // Update the distribution time to the current time.
+        self.cupcake_distribution_times.insert(user_address, current_time);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloy_primitives::address;
+    use stylus_sdk::testing::*;
+
+    const OWNER: Address = address!("0000000000000000000000000000000000000001");
+    const USER: Address = address!("0000000000000000000000000000000000000002");
+
+    fn deploy(vm: &TestVM) -> VendingMachine {
+        vm.set_sender(OWNER);
+        let mut contract = VendingMachine::from(vm);
+        contract.constructor(U256::from(5), U256::from(100), U256::from(3));
+        contract
+    }
+
+    #[test]
+    fn test_constructor() {
+        let vm = TestVM::default();
+        let contract = deploy(&vm);
+
+        assert_eq!(contract.owner(), OWNER);
+        assert_eq!(contract.cooldown(), U256::from(5));
+        assert_eq!(contract.price(), U256::from(100));
+        assert_eq!(contract.stock(), U256::from(3));
     }
 
-    // Get the cupcake balance for the specified user.
-    pub fn get_cupcake_balance_for(&self, user_address: Address) -> Uint<256, 4> {
-        // Return the user's cupcake balance from storage.
-        return self.cupcake_balances.get(user_address);
+    #[test]
+    fn test_cooldown_and_stock() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+        vm.set_block_timestamp(1000);
+
+        // Only the owner can give cupcakes away
+        vm.set_sender(USER);
+        assert!(matches!(contract.give_cupcake_to(USER), Err(VendingMachineError::NotOwner(_))));
+        vm.set_sender(OWNER);
+        assert!(contract.give_cupcake_to(USER).is_ok());
+        assert_eq!(contract.erc20.balance_of(USER), U256::from(1));
+        assert_eq!(contract.stock(), U256::from(2));
+
+        // The user has to wait for the cooldown
+        assert!(matches!(
+            contract.give_cupcake_to(USER),
+            Err(VendingMachineError::CooldownActive(CooldownActive { availableAt })) if availableAt == U256::from(1005)
+        ));
+        vm.set_block_timestamp(1005);
+        assert!(contract.give_cupcake_to(USER).is_ok());
+
+        // The vending machine runs out of cupcakes
+        vm.set_block_timestamp(1010);
+        vm.set_sender(USER);
+        vm.set_value(U256::from(200));
+        assert!(matches!(
+            contract.buy_cupcakes(U256::from(2)),
+            Err(VendingMachineError::OutOfStock(OutOfStock { available })) if available == U256::from(1)
+        ));
+
+        // Only the owner can restock
+        assert!(matches!(contract.restock(U256::from(10)), Err(VendingMachineError::NotOwner(_))));
+        vm.set_sender(OWNER);
+        assert!(contract.restock(U256::from(10)).is_ok());
+        assert_eq!(contract.stock(), U256::from(11));
+    }
+
+    #[test]
+    fn test_buy_cupcakes() {
+        let vm = TestVM::default();
+        let mut contract = deploy(&vm);
+        vm.set_block_timestamp(1000);
+        vm.set_sender(USER);
+
+        // The payment has to match the price
+        vm.set_value(U256::from(150));
+        assert!(matches!(
+            contract.buy_cupcakes(U256::from(2)),
+            Err(VendingMachineError::IncorrectPayment(IncorrectPayment { expected })) if expected == U256::from(200)
+        ));
+
+        // An amount whose price overflows cannot be bought
+        assert!(matches!(
+            contract.buy_cupcakes(U256::MAX),
+            Err(VendingMachineError::OutOfStock(OutOfStock { available })) if available == U256::from(3)
+        ));
+
+        vm.set_value(U256::from(200));
+        assert!(contract.buy_cupcakes(U256::from(2)).is_ok());
+        assert_eq!(contract.erc20.balance_of(USER), U256::from(2));
+        assert_eq!(contract.stock(), U256::from(1));
+        assert_eq!(contract.erc20.total_supply(), U256::from(2));
+
+        // Cupcakes can be transferred like any ERC-20 token
+        assert!(contract.erc20.transfer(OWNER, U256::from(1)).is_ok());
+        assert_eq!(contract.erc20.balance_of(USER), U256::from(1));
+        assert_eq!(contract.erc20.balance_of(OWNER), U256::from(1));
+
+        // Only the owner can withdraw the sales
+        vm.set_balance(vm.contract_address(), U256::from(200));
+        assert!(matches!(contract.withdraw(), Err(VendingMachineError::NotOwner(_))));
+        vm.set_sender(OWNER);
+        assert!(contract.withdraw().is_ok());
+        assert_eq!(vm.balance(OWNER), U256::from(200));
     }
 }
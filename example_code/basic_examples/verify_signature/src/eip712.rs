@@ -0,0 +1,124 @@
+//! Implementation of EIP-712 typed structured data hashing
+//!
+//! [`hash_typed_data`] returns the digest that `eth_signTypedData_v4` signs:
+//! `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`.
+//!
+//! Messages are structs declared with `sol!` that implement [`Eip712Struct`],
+//! from their `encodeType` and the encoding of each of their fields. Every field
+//! is encoded as 32 bytes by [`Eip712Field`]: strings and arrays are replaced by
+//! their hash, and nested structs by their `hashStruct`.
+//!
+//! The hashing is written out with the `keccak` hostio rather than with the
+//! `Eip712Domain` and `SolStruct` implementations of `alloy-sol-types`, which
+//! build the type strings at runtime and would make the contract much larger.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+// Imported packages
+use alloc::{string::String, vec::Vec};
+use alloy_primitives::{Address, B256, U256};
+use stylus_sdk::crypto::keccak;
+
+/// Prefix of the digest of typed data, from EIP-191 version `0x01`
+pub const TYPED_DATA_PREFIX: [u8; 2] = [0x19, 0x01];
+
+/// `encodeType` of the domain, with a name, a version, a chain ID and a verifying contract
+pub const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// A struct that can be signed as EIP-712 typed data
+pub trait Eip712Struct {
+    /// `encodeType` of the struct, followed by the struct types it references in alphabetical order
+    const ENCODE_TYPE: &'static str;
+
+    /// Returns the encoding of each field, in the order they are declared in
+    fn encode_fields(&self) -> Vec<B256>;
+
+    /// Returns `hashStruct(self)`
+    fn hash_struct(&self) -> B256 {
+        hash_struct(Self::ENCODE_TYPE, &self.encode_fields())
+    }
+}
+
+/// A value that can be a field of an [`Eip712Struct`]
+pub trait Eip712Field {
+    /// Returns the 32 byte encoding of the value in `encodeData`
+    fn encode_field(&self) -> B256;
+}
+
+impl Eip712Field for Address {
+    fn encode_field(&self) -> B256 {
+        self.into_word()
+    }
+}
+
+impl Eip712Field for U256 {
+    fn encode_field(&self) -> B256 {
+        (*self).into()
+    }
+}
+
+impl Eip712Field for B256 {
+    fn encode_field(&self) -> B256 {
+        *self
+    }
+}
+
+impl Eip712Field for bool {
+    fn encode_field(&self) -> B256 {
+        U256::from(*self as u8).into()
+    }
+}
+
+impl Eip712Field for String {
+    fn encode_field(&self) -> B256 {
+        keccak(self)
+    }
+}
+
+impl<T: Eip712Field> Eip712Field for Vec<T> {
+    fn encode_field(&self) -> B256 {
+        let data: Vec<u8> = self.iter().flat_map(|item| item.encode_field().0).collect();
+        keccak(data)
+    }
+}
+
+/// Returns `hashStruct(domain)`, the domain separator
+pub fn domain_separator(
+    name: &str,
+    version: &str,
+    chain_id: u64,
+    verifying_contract: Address,
+) -> B256 {
+    hash_struct(
+        DOMAIN_TYPE,
+        &[
+            keccak(name),
+            keccak(version),
+            U256::from(chain_id).into(),
+            verifying_contract.into_word(),
+        ],
+    )
+}
+
+/// Returns `hashStruct`, which is `keccak256(typeHash ‖ encodeData)`,
+/// from `encodeType` and the encoding of each field
+pub fn hash_struct(encode_type: &str, fields: &[B256]) -> B256 {
+    let mut data = Vec::with_capacity((fields.len() + 1) * 32);
+    data.extend_from_slice(keccak(encode_type).as_slice());
+    for field in fields {
+        data.extend_from_slice(field.as_slice());
+    }
+    keccak(data)
+}
+
+/// Returns the digest of `value` signed in the domain with `domain_separator`, as signed by `eth_signTypedData_v4`
+pub fn hash_typed_data<T: Eip712Struct>(domain_separator: B256, value: &T) -> B256 {
+    let digest_data = [
+        &TYPED_DATA_PREFIX[..],
+        domain_separator.as_slice(),
+        value.hash_struct().as_slice(),
+    ]
+    .concat();
+    keccak(digest_data)
+}
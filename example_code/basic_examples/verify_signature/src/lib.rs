@@ -3,11 +3,23 @@
 #![cfg_attr(not(any(feature = "export-abi", test)), no_main)]
 extern crate alloc;
 
+// Modules and imports
+mod eip712;
+#[cfg(feature = "k256")]
+mod recover;
+#[cfg(feature = "k256")]
+mod secp256k1;
+
+#[cfg(all(feature = "k256", feature = "export-abi"))]
+pub use recover::print_abi;
+
+use eip712::{Eip712Field, Eip712Struct};
 
 use alloy_primitives::FixedBytes;
 /// Import items from the SDK. The prelude contains common traits and macros.
-use stylus_sdk::{abi::Bytes, alloy_primitives::{address, Address, U256}, call::{self, Call}, prelude::*, crypto::keccak};
+use stylus_sdk::{abi::Bytes, alloy_primitives::{address, uint, Address, U256}, block, call::{self, Call}, contract, prelude::*, crypto::keccak, types::AddressVM};
 use alloc::string::String;
+use alloc::vec::Vec;
 use alloy_sol_types::{sol_data::{Address as SOLAddress, FixedBytes as SolFixedBytes, *}, SolType, sol};
 
 type ECRECOVERType = (SolFixedBytes<32>, Uint<8>, SolFixedBytes<32>, SolFixedBytes<32>);
@@ -15,12 +27,31 @@ type ECRECOVERType = (SolFixedBytes<32>, Uint<8>, SolFixedBytes<32>, SolFixedByt
 sol!{
     error EcrecoverCallError();
     error InvalidSignatureLength();
+    error InvalidSignatureV(uint8 v);
+    error InvalidSignatureS();
+    error UnrecoverableSignature();
+
+    // The EIP-712 typed data signed in the `eth_signTypedData_v4` example.
+    struct Person {
+        string name;
+        address[] wallets;
+    }
+
+    struct Mail {
+        Person from;
+        Person[] to;
+        string contents;
+    }
 }
 
+// The ABI representation of `Person` and `Mail`, as taken by the external methods.
+type PersonTuple = (String, Vec<Address>);
+type MailTuple = (PersonTuple, Vec<PersonTuple>, String);
+
 // Define some persistent storage using the Solidity ABI.
-// `VerifySignature` will be the entrypoint.
+// `VerifySignature` will be the entrypoint, unless the `k256` feature builds `RecoverSigner` instead.
 #[storage]  
-#[entrypoint]  
+#[cfg_attr(not(feature = "k256"), entrypoint)]  
 pub struct VerifySignature;  
 
 
@@ -28,10 +59,29 @@ pub struct VerifySignature;
 pub enum VerifySignatureError {
     EcrecoverCallError(EcrecoverCallError),
     InvalidSignatureLength(InvalidSignatureLength),
+    InvalidSignatureV(InvalidSignatureV),
+    InvalidSignatureS(InvalidSignatureS),
+    UnrecoverableSignature(UnrecoverableSignature),
 }
 
+sol_interface! {
+    /// Allows calls to the `isValidSignature` method of contract wallets implementing ERC-1271.
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4);
+    }
+}
+
+/// Magic value returned by `isValidSignature` for a valid signature, its selector.
+const ERC1271_MAGIC_VALUE: u32 = 0x1626ba7e;
+
 const ECRECOVER: Address = address!("0000000000000000000000000000000000000001");
-const SIGNED_MESSAGE_HEAD: &'static str = "\x19Ethereum Signed Message:\n32";
+const SIGNED_MESSAGE_HEAD: &str = "\x19Ethereum Signed Message:\n32";
+// Half the order of the secp256k1 curve. Signatures with a larger `s` are rejected, as in EIP-2,
+// because `(r, n - s)` is another valid signature of the same message.
+const SECP256K1N_HALF: U256 = uint!(0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0_U256);
+// The name and version of the EIP-712 signing domain.
+const EIP712_NAME: &str = "VerifySignature";
+const EIP712_VERSION: &str = "1";
 
 /// Declare that `VerifySignature` is a contract with the following external methods.
 #[public]
@@ -57,8 +107,8 @@ impl VerifySignature {
         message: String,
         nonce: U256,
     ) -> FixedBytes<32> {
-        let message_data = [&to.to_vec(), &amount.to_be_bytes_vec(), message.as_bytes(), &nonce.to_be_bytes_vec()].concat();
-        keccak(message_data).into()
+        let message_data = [to.as_slice(), &amount.to_be_bytes_vec(), message.as_bytes(), &nonce.to_be_bytes_vec()].concat();
+        keccak(message_data)
     }
 
     /* 3. Sign message hash
@@ -73,8 +123,8 @@ impl VerifySignature {
     0x993dab3dd91f5c6dc28e17439be475478f5635c92a56e17e82349d3fb2f166196f466c0b4e0c146f285204f0dcb13e5ae67bc33f4b888ec32dfe0a063e8f3f781b
     */
     pub fn get_eth_signed_message_hash(&self, message_hash: FixedBytes<32>) -> FixedBytes<32> {
-        let message_to_be_decoded = [SIGNED_MESSAGE_HEAD.as_bytes(), &message_hash.to_vec()].concat();
-        keccak(message_to_be_decoded).into()
+        let message_to_be_decoded = [SIGNED_MESSAGE_HEAD.as_bytes(), message_hash.as_slice()].concat();
+        keccak(message_to_be_decoded)
     }
 
     /* 4. Verify signature
@@ -97,10 +147,28 @@ impl VerifySignature {
     ) -> Result<bool, VerifySignatureError> {
         let message_hash = self.get_message_hash(to, amount, message, nonce);
         let eth_signed_message_hash = self.get_eth_signed_message_hash(message_hash);
-        match self.recover_signer(eth_signed_message_hash, signature) {
-            Ok(recovered_signer) => Ok(recovered_signer == signer),
-            Err(err) => Err(err),
+        self.is_valid_signature_now(signer, eth_signed_message_hash, signature)
+    }
+
+    /// Check that `signature` of `hash` is valid for `signer`, like OpenZeppelin's `SignatureChecker`.
+    /// Contract wallets, such as Safes, have code and validate signatures themselves with ERC-1271,
+    /// for any other signer the signature is recovered with ecrecover.
+    pub fn is_valid_signature_now(
+        &self,
+        signer: Address,
+        hash: FixedBytes<32>,
+        signature: Bytes,
+    ) -> Result<bool, VerifySignatureError> {
+        if signer.has_code() {
+            // A wallet that reverts or returns anything but the magic value rejects the signature.
+            let wallet = IERC1271::new(signer);
+            return Ok(match wallet.is_valid_signature(Call::new(), hash, signature.0.into()) {
+                Ok(magic_value) => u32::from_be_bytes(magic_value.0) == ERC1271_MAGIC_VALUE,
+                Err(_) => false,
+            });
         }
+        let recovered_signer = self.recover_signer(hash, signature)?;
+        Ok(recovered_signer == signer)
     }
 
     pub fn recover_signer(
@@ -108,11 +176,12 @@ impl VerifySignature {
         eth_signed_message_hash: FixedBytes<32>,
         signature: Bytes
     ) -> Result<Address, VerifySignatureError> {
-        let (r, s, v) = self.split_signature(signature);
+        let (r, s, v) = self.split_signature(signature)?;
         self.ecrecover_call(eth_signed_message_hash, v, r, s)
     }
 
     /// Invoke the ECRECOVER precompile.
+    /// The precompile returns no data when the signature is not recoverable.
     pub fn ecrecover_call(
         &self,
         hash: FixedBytes<32>,
@@ -120,23 +189,191 @@ impl VerifySignature {
         r: FixedBytes<32>,
         s: FixedBytes<32>,
     ) -> Result<Address, VerifySignatureError> {
-        let data = (hash, v, r, s);
-        let encoded_data = ECRECOVERType::abi_encode(&data);
-        match call::static_call(Call::new(), ECRECOVER, &encoded_data) {
-            Ok(result) => Ok(SOLAddress::abi_decode(&result, false).unwrap()),
-            Err(_) => Err(VerifySignatureError::EcrecoverCallError(EcrecoverCallError{})),
-        }
+        ecrecover_call(hash, v, r, s)
     }
 
-
+    /// Split a signature into `(r, s, v)`, with `v` normalized to 27 or 28.
+    /// Accepts 65-byte `r ‖ s ‖ v` signatures and EIP-2098 64-byte compact `r ‖ vs` signatures,
+    /// where the top bit of `vs` is the parity of `v`.
     pub fn split_signature(
         &self,
         signature: Bytes
-    ) -> (FixedBytes<32>, FixedBytes<32>, u8) {
-        let r = FixedBytes::from_slice(&signature[0..32]);
-        let s = FixedBytes::from_slice(&signature[32..64]);
-        let v = signature[64];
-        (r, s, v)
+    ) -> Result<(FixedBytes<32>, FixedBytes<32>, u8), VerifySignatureError> {
+        split_signature(signature)
+    }
+
+    /* 5. Verify EIP-712 typed data
+    The domain is { name: "VerifySignature", version: "1", chainId, verifyingContract: <this contract> }
+    and the message a `Mail`, for example
+    {
+        from: { name: "Cow", wallets: ["0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"] },
+        to: [{ name: "Bob", wallets: ["0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"] }],
+        contents: "Hello, Bob!"
+    }
+
+    # using browser
+    ethereum.request({ method: "eth_signTypedData_v4", params: [account, JSON.stringify(typedData)]}).then(console.log)
+    */
+    pub fn domain_separator(&self) -> FixedBytes<32> {
+        eip712::domain_separator(EIP712_NAME, EIP712_VERSION, block::chainid(), contract::address())
+    }
+
+    // The digest `eth_signTypedData_v4` signs for `mail` in the domain of this contract.
+    pub fn get_typed_data_hash(&self, mail: MailTuple) -> FixedBytes<32> {
+        eip712::hash_typed_data(self.domain_separator(), &mail_from_tuple(mail))
+    }
+
+    pub fn verify_typed_data(
+        &self,
+        signer: Address,
+        mail: MailTuple,
+        signature: Bytes,
+    ) -> Result<bool, VerifySignatureError> {
+        let typed_data_hash = self.get_typed_data_hash(mail);
+        self.is_valid_signature_now(signer, typed_data_hash, signature)
+    }
+}
+
+impl Eip712Struct for Person {
+    const ENCODE_TYPE: &'static str = "Person(string name,address[] wallets)";
+
+    fn encode_fields(&self) -> Vec<FixedBytes<32>> {
+        vec![self.name.encode_field(), self.wallets.encode_field()]
+    }
+}
+
+impl Eip712Field for Person {
+    fn encode_field(&self) -> FixedBytes<32> {
+        self.hash_struct()
+    }
+}
+
+impl Eip712Struct for Mail {
+    const ENCODE_TYPE: &'static str =
+        "Mail(Person from,Person[] to,string contents)Person(string name,address[] wallets)";
+
+    fn encode_fields(&self) -> Vec<FixedBytes<32>> {
+        vec![self.from.encode_field(), self.to.encode_field(), self.contents.encode_field()]
+    }
+}
+
+// Invokes the ECRECOVER precompile, see `VerifySignature::ecrecover_call`.
+fn ecrecover_call(
+    hash: FixedBytes<32>,
+    v: u8,
+    r: FixedBytes<32>,
+    s: FixedBytes<32>,
+) -> Result<Address, VerifySignatureError> {
+    let data = (hash, v, r, s);
+    let encoded_data = ECRECOVERType::abi_encode(&data);
+    match call::static_call(Call::new(), ECRECOVER, &encoded_data) {
+        Ok(result) => match SOLAddress::abi_decode(&result, false) {
+            Ok(signer) if signer != Address::ZERO => Ok(signer),
+            _ => Err(VerifySignatureError::UnrecoverableSignature(UnrecoverableSignature {})),
+        },
+        Err(_) => Err(VerifySignatureError::EcrecoverCallError(EcrecoverCallError{})),
+    }
+}
+
+// Splits a signature into `(r, s, v)`, see `VerifySignature::split_signature`.
+fn split_signature(signature: Bytes) -> Result<(FixedBytes<32>, FixedBytes<32>, u8), VerifySignatureError> {
+    let (s, v) = match signature.len() {
+        65 => {
+            let v = match signature[64] {
+                v @ (0 | 1) => v + 27,
+                v @ (27 | 28) => v,
+                v => return Err(VerifySignatureError::InvalidSignatureV(InvalidSignatureV { v })),
+            };
+            (U256::from_be_slice(&signature[32..64]), v)
+        }
+        64 => {
+            let vs = U256::from_be_slice(&signature[32..64]);
+            let v = if vs.bit(255) { 28 } else { 27 };
+            (vs & (U256::MAX >> 1), v)
+        }
+        _ => return Err(VerifySignatureError::InvalidSignatureLength(InvalidSignatureLength {})),
+    };
+    if s > SECP256K1N_HALF {
+        return Err(VerifySignatureError::InvalidSignatureS(InvalidSignatureS {}));
+    }
+    let r = FixedBytes::from_slice(&signature[0..32]);
+    let s = FixedBytes::from(s.to_be_bytes::<32>());
+    Ok((r, s, v))
+}
+
+// Converts the ABI representation of a `Mail` into the `sol!` struct.
+fn mail_from_tuple((from, to, contents): MailTuple) -> Mail {
+    let person = |(name, wallets): PersonTuple| Person { name, wallets };
+    Mail {
+        from: person(from),
+        to: to.into_iter().map(person).collect(),
+        contents,
+    }
+}
+
+// Keccak is a hostio unless the SDK is built with `export-abi`, which computes it natively.
+#[cfg(all(test, feature = "export-abi"))]
+mod tests {
+    use super::*;
+    use alloy_sol_types::SolStruct;
+    use stylus_sdk::alloy_primitives::b256;
+
+    #[test]
+    fn test_encode_type() {
+        assert_eq!(Person::ENCODE_TYPE, <Person as SolStruct>::eip712_encode_type());
+        assert_eq!(Mail::ENCODE_TYPE, <Mail as SolStruct>::eip712_encode_type());
+    }
+
+    // The `Mail` example of `signTypedData` v4 in eth-sig-util.
+    #[test]
+    fn test_hash_typed_data() {
+        let domain_separator = eip712::domain_separator(
+            "Ether Mail",
+            "1",
+            1,
+            address!("CcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"),
+        );
+        assert_eq!(
+            domain_separator,
+            b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+
+        let mail = Mail {
+            from: Person {
+                name: "Cow".into(),
+                wallets: vec![
+                    address!("CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+                    address!("DeaDbeefdEAdbeefdEadbEEFdeadbeEFdEaDbeeF"),
+                ],
+            },
+            to: vec![Person {
+                name: "Bob".into(),
+                wallets: vec![
+                    address!("bBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+                    address!("B0BdaBea57B0BDABeA57b0bdABEA57b0BDabEa57"),
+                    address!("B0B0b0b0b0b0B000000000000000000000000000"),
+                ],
+            }],
+            contents: "Hello, Bob!".into(),
+        };
+        assert_eq!(mail.hash_struct(), mail.eip712_hash_struct());
+        assert_eq!(
+            eip712::hash_typed_data(domain_separator, &mail),
+            b256!("a85c2e2b118698e88db68a8105b794a8cc7cec074e89ef991cb4f5f533819cc2")
+        );
+    }
+
+    // The `personal_sign` example of `verify`, recovered in WASM.
+    #[cfg(feature = "k256")]
+    #[test]
+    fn test_recover_k256() {
+        let message_hash = b256!("cf36ac4f97dc10d91fc2cbb20d718e94a8cbfe0f82eaedc6a4aa38946fb797cd");
+        let hash = keccak([SIGNED_MESSAGE_HEAD.as_bytes(), message_hash.as_slice()].concat());
+        let signature = hex::decode("993dab3dd91f5c6dc28e17439be475478f5635c92a56e17e82349d3fb2f166196f466c0b4e0c146f285204f0dcb13e5ae67bc33f4b888ec32dfe0a063e8f3f781b").unwrap();
+        let Ok((r, s, v)) = split_signature(signature.into()) else { panic!("invalid signature") };
+        assert_eq!(
+            secp256k1::recover(hash, v, r, s),
+            Some(address!("B273216C05A8c0D4F0a4Dd0d7Bae1D2EfFE636dd"))
+        );
     }
-            
 }
@@ -0,0 +1,49 @@
+//! A recover-only contract, the entrypoint of the crate with the `k256` feature
+//!
+//! `RecoverSigner` recovers signers both in WASM with k256 and with the
+//! ECRECOVER precompile, so that their costs can be compared in one contract.
+//! It only has these two methods because k256 is too large to fit in a
+//! contract next to the rest of `VerifySignature` under the 24 KB limit.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+// Imported packages
+use alloy_primitives::{Address, FixedBytes};
+use stylus_sdk::{abi::Bytes, prelude::*};
+
+use crate::{ecrecover_call, secp256k1, split_signature, UnrecoverableSignature, VerifySignatureError};
+
+#[storage]
+#[entrypoint]
+pub struct RecoverSigner;
+
+/// Declare that `RecoverSigner` is a contract with the following external methods.
+#[public]
+impl RecoverSigner {
+    /* 6. Recover the signer in WASM
+    By default signers are recovered with the ECRECOVER precompile. With the `k256` feature,
+    the crate builds this contract instead of `VerifySignature`, and `recover_signer` recovers them
+    in WASM with the k256 crate, while `ecrecover_call` still invokes the precompile.
+    Their costs can be compared by estimating the gas of `recoverSigner` and `ecrecoverCall` with the same signature.
+    */
+    pub fn recover_signer(
+        &self,
+        eth_signed_message_hash: FixedBytes<32>,
+        signature: Bytes,
+    ) -> Result<Address, VerifySignatureError> {
+        let (r, s, v) = split_signature(signature)?;
+        secp256k1::recover(eth_signed_message_hash, v, r, s)
+            .ok_or(VerifySignatureError::UnrecoverableSignature(UnrecoverableSignature {}))
+    }
+
+    /// Invoke the ECRECOVER precompile.
+    pub fn ecrecover_call(
+        &self,
+        hash: FixedBytes<32>,
+        v: u8,
+        r: FixedBytes<32>,
+        s: FixedBytes<32>,
+    ) -> Result<Address, VerifySignatureError> {
+        ecrecover_call(hash, v, r, s)
+    }
+}
@@ -0,0 +1,26 @@
+//! Recovery of secp256k1 signers in WASM, without the ecrecover precompile
+//!
+//! [`recover`] recovers the public key of a signature with the `no_std`
+//! [`k256`] crate, and derives the signer's address from it as Ethereum does:
+//! the last 20 bytes of the keccak256 hash of the uncompressed public key.
+//!
+//! It is only compiled with the `k256` feature, so that contracts which do
+//! not use it are not larger for it.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+// Imported packages
+use alloy_primitives::{Address, B256};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use stylus_sdk::crypto::keccak;
+
+/// Returns the address that signed `hash`, or `None` if the signature is not recoverable.
+/// `v` is 27 or 28, as taken by the ecrecover precompile.
+pub fn recover(hash: B256, v: u8, r: B256, s: B256) -> Option<Address> {
+    let signature = Signature::from_scalars(r.0, s.0).ok()?;
+    let recovery_id = RecoveryId::from_byte(v.checked_sub(27)?)?;
+    let public_key = VerifyingKey::recover_from_prehash(hash.as_slice(), &signature, recovery_id).ok()?;
+    // Skip the 0x04 tag of the uncompressed SEC1 encoding.
+    let public_key_hash = keccak(&public_key.to_encoded_point(false).as_bytes()[1..]);
+    Some(Address::from_slice(&public_key_hash[12..]))
+}
@@ -177,11 +177,31 @@ export const applications = [
     title: "English Auction",
     description: "An example implementation of the English Auction in Rust using Arbitrum Stylus.",
   },
+  {
+    route: "/applications/auction_house",
+    title: "Auction House",
+    description: "An example implementation of an NFT auction house running many English auctions in Rust using Arbitrum Stylus.",
+  },
+  {
+    route: "/applications/dutch_auction",
+    title: "Dutch Auction",
+    description: "An example implementation of the Dutch Auction in Rust using Arbitrum Stylus.",
+  },
+  {
+    route: "/applications/sealed_bid_auction",
+    title: "Sealed-Bid Auction",
+    description: "An example implementation of a sealed-bid commit-reveal (Vickrey) auction in Rust using Arbitrum Stylus.",
+  },
   {
     route: '/applications/multi_sig',
     title: 'MultiSig',
     description: 'An example implementation of the MultiSig wallet in Rust using Arbitrum Stylus.',
   },
+  {
+    route: '/applications/spending_limit',
+    title: 'Spending Limit',
+    description: 'An example implementation of a daily spending-limit module for the MultiSig wallet in Rust using Arbitrum Stylus.',
+  },
   {
     route: '/applications/vending_machine',
     title: 'Vending Machine',